@@ -0,0 +1,79 @@
+use gravity_rust::sim::broadphase::BroadPhase;
+use gravity_rust::sim::object::OBJECT_RADIUS;
+use gravity_rust::sim::solver::Solver;
+use gravity_rust::vec2;
+
+use std::time::Instant;
+
+/// Object counts to benchmark.
+const SIZES: [usize; 4] = [100, 1_000, 5_000, 10_000];
+
+/// Number of times each collision pass is repeated; the fastest run is reported.
+const REPEATS: usize = 3;
+
+/// Average area per object, in pixels squared. Keeps the density constant as the object count grows.
+const AREA_PER_OBJECT: f64 = 2_500.0;
+
+/// Small xorshift generator so runs are reproducible without pulling in a dependency.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn scene(n: usize) -> Solver {
+    let side = (n as f64 * AREA_PER_OBJECT).sqrt();
+    let mut solver = Solver::new(0.0, side, 0.0, side);
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+
+    for _ in 0..n {
+        let position = vec2!(rng.next_f64() * side, rng.next_f64() * side);
+        let velocity = vec2!(rng.next_f64() - 0.5, rng.next_f64() - 0.5) * 400.0;
+        solver.add_object(position, velocity, 1.0);
+    }
+
+    // give every object a displacement for the step without resolving it
+    solver.integrate(1.0 / 60.0);
+    solver
+}
+
+/// Times the collision pass of `Solver` with every broad phase and prints a table.
+pub fn run() {
+    let broad_phases = [
+        ("brute force", BroadPhase::BruteForce),
+        (
+            "uniform grid",
+            BroadPhase::UniformGrid {
+                cell_size: 4.0 * OBJECT_RADIUS,
+            },
+        ),
+        ("sort and sweep", BroadPhase::SortAndSweep),
+    ];
+
+    println!(
+        "{:>8} {:>16} {:>12} {:>10}",
+        "objects", "broad phase", "time (ms)", "contacts"
+    );
+    for n in SIZES {
+        let mut solver = scene(n);
+
+        for (name, broad_phase) in broad_phases {
+            solver.broad_phase = broad_phase;
+
+            let mut best = f64::INFINITY;
+            let mut contacts = 0;
+            for _ in 0..REPEATS {
+                let start = Instant::now();
+                contacts = solver.get_collisions().len();
+                best = best.min(start.elapsed().as_secs_f64() * 1000.0);
+            }
+
+            println!("{:>8} {:>16} {:>12.3} {:>10}", n, name, best, contacts);
+        }
+    }
+}
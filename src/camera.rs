@@ -0,0 +1,59 @@
+extern crate piston_window;
+
+use gravity_rust::Vec2;
+
+use piston_window::math::Matrix2d;
+use piston_window::Transformed;
+
+/// Smallest and largest zoom factors.
+const MIN_ZOOM: f64 = 0.1;
+const MAX_ZOOM: f64 = 10.0;
+
+/// Which part of the world is shown in the window.
+///
+/// World coordinates are the solver's; screen coordinates are window pixels. With the default camera the two are
+/// the same.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    /// World position shown in the top left corner of the window.
+    pub offset: Vec2,
+
+    /// Window pixels per world unit.
+    pub zoom: f64,
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera {
+            offset: Vec2 { x: 0.0, y: 0.0 },
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Camera {
+    /// World position shown at window pixel `screen`.
+    pub fn to_world(self, screen: Vec2) -> Vec2 {
+        self.offset + screen * (1.0 / self.zoom)
+    }
+
+    /// Transform for drawing in world coordinates, given the window's own `transform`.
+    pub fn transform(&self, transform: Matrix2d) -> Matrix2d {
+        transform
+            .zoom(self.zoom)
+            .trans(-self.offset.x, -self.offset.y)
+    }
+
+    /// Moves the view by `delta` window pixels, so the world follows a drag.
+    pub fn pan(&mut self, delta: Vec2) {
+        self.offset -= delta * (1.0 / self.zoom);
+    }
+
+    /// Zooms in by `factor` (or out, below `1.0`), keeping the world position under window pixel `screen` in place.
+    pub fn zoom_at(&mut self, screen: Vec2, factor: f64) {
+        let anchor = self.to_world(screen);
+
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = anchor - screen * (1.0 / self.zoom);
+    }
+}
@@ -0,0 +1,152 @@
+use gravity_rust::sim::export::{ColumnarExporter, CsvExporter, Exporter};
+use gravity_rust::sim::force::ForceModel;
+use gravity_rust::sim::integrator;
+use gravity_rust::sim::replay::Recording;
+use gravity_rust::sim::scene::Scene;
+use gravity_rust::sim::solver::Solver;
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+const USAGE: &str =
+    "usage: gravity-rust headless --scene <file> [--steps <n>] [--dt <seconds>] [--every <n>] \
+[--integrator <name>] [--force-model <uniform|mutual|barnes-hut>] [--format <csv|columnar>] [--max-rows <n>] \
+[--output <file>]";
+
+/// Options for a headless run.
+struct Options {
+    scene: String,
+    steps: usize,
+    dt: f64,
+    every: usize,
+    integrator: Option<String>,
+    force_model: Option<String>,
+    format: String,
+    max_rows: u64,
+    output: Option<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            scene: String::new(),
+            steps: 600,
+            dt: 1.0 / 120.0,
+            every: 1,
+            integrator: None,
+            force_model: None,
+            format: "csv".to_string(),
+            max_rows: crate::EXPORT_MAX_ROWS,
+            output: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {}", flag))?;
+            let invalid = || format!("invalid value for {}: {}", flag, value);
+
+            match flag.as_str() {
+                "--scene" => options.scene = value.clone(),
+                "--steps" => options.steps = value.parse().map_err(|_| invalid())?,
+                "--dt" => options.dt = value.parse().map_err(|_| invalid())?,
+                "--every" => options.every = value.parse().map_err(|_| invalid())?,
+                "--integrator" => options.integrator = Some(value.clone()),
+                "--force-model" => options.force_model = Some(value.clone()),
+                "--format" => options.format = value.clone(),
+                "--max-rows" => options.max_rows = value.parse().map_err(|_| invalid())?,
+                "--output" => options.output = Some(value.clone()),
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+
+        if options.scene.is_empty() {
+            return Err("missing --scene".to_string());
+        }
+        if !(options.dt > 0.0 && options.dt.is_finite()) {
+            return Err("--dt must be a positive number".to_string());
+        }
+        if options.every == 0 {
+            return Err("--every must be at least 1".to_string());
+        }
+        if options.format != "csv" && options.format != "columnar" {
+            return Err(format!("unknown format {}", options.format));
+        }
+        Ok(options)
+    }
+}
+
+/// Runs a scene without a window, writing the state of every object as CSV or in the binary columnar format.
+///
+/// `args` are the command line arguments following `headless`.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args).map_err(|e| format!("{}\n{}", e, USAGE))?;
+
+    let json = fs::read_to_string(&options.scene)
+        .map_err(|e| format!("could not read {}: {}", options.scene, e))?;
+    let scene =
+        Scene::from_json(&json).map_err(|e| format!("invalid scene {}: {}", options.scene, e))?;
+
+    let mut solver = scene.to_solver();
+    if let Some(name) = &options.integrator {
+        solver.integrator =
+            integrator::by_name(name).ok_or_else(|| format!("unknown integrator {}", name))?;
+    }
+    if let Some(name) = &options.force_model {
+        solver.force_model =
+            ForceModel::by_name(name).ok_or_else(|| format!("unknown force model {}", name))?;
+    }
+
+    let output: Box<dyn Write> = match &options.output {
+        Some(path) => {
+            Box::new(File::create(path).map_err(|e| format!("could not create {}: {}", path, e))?)
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+    let output = BufWriter::new(output);
+
+    let mut exporter: Box<dyn Exporter> = match options.format.as_str() {
+        "columnar" => Box::new(ColumnarExporter::new(output).with_max_rows(options.max_rows)),
+        _ => Box::new(CsvExporter::new(output)),
+    };
+
+    write_steps(exporter.as_mut(), &mut solver, &options).map_err(|e| e.to_string())
+}
+
+fn write_steps(
+    exporter: &mut dyn Exporter,
+    solver: &mut Solver,
+    options: &Options,
+) -> io::Result<()> {
+    for step in 0..=options.steps {
+        if step > 0 {
+            solver.solve_all(options.dt);
+        }
+
+        if step % options.every == 0 {
+            exporter.write_step(step as u64, solver)?;
+        }
+    }
+
+    exporter.finish()
+}
+
+/// Plays a recording back without a window and checks it ends in the recorded state.
+///
+/// `args` are the command line arguments following `replay`: just the path of the recording.
+pub fn replay(args: &[String]) -> Result<(), String> {
+    let path = match args {
+        [path] => path,
+        _ => return Err("usage: gravity-rust replay <file>".to_string()),
+    };
+
+    let json = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    let recording =
+        Recording::from_json(&json).map_err(|e| format!("invalid recording {}: {}", path, e))?;
+
+    let solver = recording.replay().map_err(|e| e.to_string())?;
+    println!("replay matched state hash {:016x}", solver.state_hash());
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+//! 2D gravity sandbox physics.
+//!
+//! Everything needed to run a simulation lives in [`sim`]; the most commonly used types are re-exported here.
+//! Nothing in this library draws anything, so it can be embedded in other tools or run on a server.
+//!
+//! ```
+//! use gravity_rust::{vec2, Object, Solver};
+//!
+//! let mut solver = Solver::new(0.0, 800.0, 0.0, 400.0);
+//! let ball = solver.add(Object::new(vec2!(100, 100), vec2!(200, 0), 10.0));
+//!
+//! for _ in 0..120 {
+//!     solver.solve_all(1.0 / 120.0);
+//! }
+//!
+//! assert!(solver.get(ball).unwrap().position.x > 100.0);
+//! ```
+
+pub mod sim;
+
+pub use sim::arena::ObjectId;
+pub use sim::object::Object;
+pub use sim::segment::Segment;
+pub use sim::solver::Solver;
+pub use sim::vec2::Vec2;
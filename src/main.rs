@@ -1,42 +1,267 @@
-mod sim;
+mod bench;
+mod camera;
+mod headless;
 mod window;
 
-extern crate lazy_static;
 extern crate piston_window;
 
-use crate::sim::object::Object;
+use crate::camera::Camera;
 use crate::window::create_window;
 
-use sim::segment::Segment;
-use sim::solver::Solver;
-use sim::vec2;
-use sim::vec2::Vec2;
+use gravity_rust::sim::boundary::{Boundaries, BoundaryMode};
+use gravity_rust::sim::collider::Collider;
+use gravity_rust::sim::constraint::Link;
+use gravity_rust::sim::diagnostics::History;
+use gravity_rust::sim::export::{ColumnarExporter, CsvExporter, Exporter};
+use gravity_rust::sim::force::ForceModel;
+use gravity_rust::sim::integrator::{
+    Integrator, Leapfrog, RungeKutta4, SemiImplicitEuler, VelocityVerlet,
+};
+use gravity_rust::sim::object::{BodyType, OBJECT_RADIUS};
+use gravity_rust::sim::preset::Preset;
+use gravity_rust::sim::replay::{self, Entry, Recording};
+use gravity_rust::sim::scene::{Scene, SceneBounds, SceneConstraint, SceneObject};
+use gravity_rust::{vec2, Object, ObjectId, Segment, Solver, Vec2};
 
 use piston_window::ellipse::circle;
 use piston_window::Button as ButtonType;
 use piston_window::Motion::{MouseCursor, MouseScroll};
 use piston_window::*;
 
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::sync::Arc;
 use std::time::Instant;
 
-const BALL_RADIUS: f64 = 10.0;
+/// Color static colliders are drawn in.
+const COLLIDER_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+/// Color the bounds are outlined in.
+const BOUNDS_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
+
+/// Color of the line drawn across each ball to show how far it has turned.
+const MARKER_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+/// Color links between balls are drawn in.
+const CONSTRAINT_COLOR: [f32; 4] = [0.4, 0.8, 1.0, 0.8];
+
+/// Color of the ring around static objects.
+const STATIC_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+
+/// Color of the ring around kinematic objects.
+const KINEMATIC_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 0.9];
+
+/// How quickly a ball dragged with Ctrl held catches up with the mouse, as a fraction of the distance per second.
+const GRAB_SPEED: f64 = 10.0;
+
+/// Furthest the mouse can move between pressing and releasing for it to count as a click.
+/// Measured in pixels
+const CLICK_DISTANCE: f64 = 5.0;
+
+/// Opacity of the inside of soft bodies.
+const SOFT_BODY_ALPHA: f32 = 0.3;
+
+/// Stiffness and damping of the springs made with a Shift+drag between two balls.
+const SPRING_STIFFNESS: f64 = 200.0;
+const SPRING_DAMPING: f64 = 2.0;
+
+/// How opaque sleeping balls are drawn, relative to their own color.
+const SLEEPING_ALPHA: f32 = 0.5;
+
+/// How much one notch of Ctrl+scroll zooms in or out.
+const ZOOM_STEP: f64 = 1.1;
+
+/// Shortest wall that can be drawn with a right-drag; anything shorter is taken as a stray click.
+/// Measured in pixels
+const MIN_WALL_LENGTH: f64 = 5.0;
+
+/// Colors given to new balls, in turn.
+const BALL_COLORS: [[f32; 4]; 5] = [
+    [1.0, 1.0, 1.0, 1.0],
+    [0.95, 0.45, 0.4, 1.0],
+    [0.45, 0.8, 0.5, 1.0],
+    [0.4, 0.6, 0.95, 1.0],
+    [0.95, 0.8, 0.35, 1.0],
+];
+
+/// Where Ctrl+S and Ctrl+O save and load when no `--scene` is given.
+const DEFAULT_SCENE_PATH: &str = "scene.json";
+
+/// Where R saves the recording when no `--record` is given.
+const DEFAULT_RECORDING_PATH: &str = "recording.json";
+
+/// Where E exports trajectories when no `--export` is given.
+const DEFAULT_EXPORT_PATH: &str = "trajectory.csv";
+
+/// Number of snapshots plotted by the diagnostics HUD; at one per frame, about ten seconds.
+const HUD_SAMPLES: usize = 600;
+
+/// Size of the diagnostics HUD in the top left corner.
+/// Measured in pixels
+const HUD_WIDTH: f64 = 240.0;
+const HUD_HEIGHT: f64 = 80.0;
+
+/// Colors of the energy, momentum and angular momentum lines in the diagnostics HUD.
+const HUD_COLORS: [[f32; 4]; 3] = [
+    [0.95, 0.45, 0.4, 1.0],
+    [0.45, 0.8, 0.5, 1.0],
+    [0.4, 0.6, 0.95, 1.0],
+];
+
+/// Most rows a columnar export keeps in memory before it stops and is written out; at 64 bytes a row, 64 MB. Also the
+/// default of `headless --max-rows`.
+const EXPORT_MAX_ROWS: u64 = 1_000_000;
+
+/// Opens an exporter writing to `path`, picking the format from its extension.
+fn create_exporter(path: &str) -> Result<Box<dyn Exporter>, String> {
+    let file = File::create(path).map_err(|e| format!("could not create {}: {}", path, e))?;
+    let output = BufWriter::new(file);
+
+    if path.ends_with(".grvc") {
+        Ok(Box::new(
+            ColumnarExporter::new(output).with_max_rows(EXPORT_MAX_ROWS),
+        ))
+    } else {
+        Ok(Box::new(CsvExporter::new(output)))
+    }
+}
+
+/// Reads a scene file.
+fn load_scene(path: &str) -> Result<Scene, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+
+    Scene::from_json(&json).map_err(|e| format!("invalid scene {}: {}", path, e))
+}
+
+/// Reads a recording file.
+fn load_recording(path: &str) -> Result<Recording, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+
+    Recording::from_json(&json).map_err(|e| format!("invalid recording {}: {}", path, e))
+}
+
+/// Value following `flag` on the command line, or `default` if the flag isn't there.
+fn flag_value(args: &[String], flag: &str, default: &str) -> String {
+    match args.iter().position(|a| a == flag).map(|i| args.get(i + 1)) {
+        Some(Some(value)) => value.clone(),
+        Some(None) => {
+            eprintln!("missing value for {}", flag);
+            std::process::exit(1);
+        }
+        None => default.to_string(),
+    }
+}
+
+/// Applies `event` to the solver, adding it to the recording if one is running.
+fn apply(event: replay::Event, solver: &mut Solver, recording: &mut Option<Recording>) {
+    event.apply(solver);
+
+    if let Some(recording) = recording {
+        recording.event(event);
+    }
+}
 
 fn main() {
+    // subcommands that run without opening a window
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        // `cargo run --release -- bench` times the collision pass
+        Some("bench") => {
+            bench::run();
+            return;
+        }
+        // `cargo run --release -- headless --scene <file>` runs a scene and prints every step
+        Some("headless") => {
+            if let Err(e) = headless::run(&args[2..]) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        // `cargo run --release -- replay <file>` plays a recording back and checks it ends in the same state
+        Some("replay") => {
+            if let Err(e) = headless::replay(&args[2..]) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    // `cargo run -- --scene <file>` starts from a scene file, which is also where Ctrl+S and Ctrl+O save and load
+    let scene_flag = args.iter().any(|a| a == "--scene");
+    let scene_path = flag_value(&args, "--scene", DEFAULT_SCENE_PATH);
+
+    // `cargo run -- --record <file>` sets where R saves recordings
+    let recording_path = flag_value(&args, "--record", DEFAULT_RECORDING_PATH);
+
+    // `cargo run -- --export <file>` sets where E exports trajectories; files ending in `.grvc` use the binary
+    // columnar format, anything else CSV
+    let export_path = flag_value(&args, "--export", DEFAULT_EXPORT_PATH);
+
+    // the export being written, if E has been pressed, and the number of steps written to it so far
+    let mut export: Option<(Box<dyn Exporter>, u64)> = None;
+
+    // the recording being made, if R has been pressed
+    let mut recording: Option<Recording> = None;
+
+    // `cargo run -- --replay <file>` plays a recording back in the window: the recording and how far into its
+    // timeline playback is
+    let mut playback: Option<(Recording, usize)> = None;
+    if args.iter().any(|a| a == "--replay") {
+        let path = flag_value(&args, "--replay", "");
+        let loaded = load_recording(&path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        playback = Some((loaded, 0));
+    }
+
     let mut time_scaling_factor: f64 = 1.0;
 
+    // whether a control key is held, for the save and load shortcuts
+    let mut ctrl_down: bool = false;
+
+    // whether a shift key is held, to link balls with a spring instead of throwing a new one
+    let mut shift_down: bool = false;
+
     // whether or not to show the arrow vectors
     let mut show_vectors: bool = false;
 
     let mut show_predictions: bool = true;
 
-    // mouse position
+    // whether to plot energy and momentum drift, the snapshots plotted, and what the solver looked like when they
+    // started (the number of objects, force model and integrator), since changing any of those changes the totals
+    let mut show_hud: bool = false;
+    let mut history = History::new(HUD_SAMPLES);
+    let mut history_started = (0, ForceModel::default(), "");
+
+    // mouse position, in window pixels
     let mut mouse_x: f64 = 0.0;
     let mut mouse_y: f64 = 0.0;
 
-    // where the mouse was pressed and depressed
+    // the part of the world shown in the window, and whether it is being dragged around with the middle button
+    let mut camera = Camera::default();
+    let mut panning: bool = false;
+
+    // where the mouse was pressed and depressed, in world coordinates
     let mut mouse_down_position: Option<Vec2> = None;
     let mut mouse_up_position: Option<Vec2> = None;
 
+    // where the right mouse button was pressed, in world coordinates, while a wall is being drawn
+    let mut wall_start_position: Option<Vec2> = None;
+
+    // the ball a spring is being dragged from with Shift held
+    let mut spring_start: Option<ObjectId> = None;
+
+    // the ball being dragged with Ctrl held, its body type before it was grabbed, and where it was grabbed
+    let mut grabbed: Option<(ObjectId, BodyType, Vec2)> = None;
+
+    // what a left drag throws: a ball, or one of the presets when set
+    let presets = Preset::defaults();
+    let mut spawn_preset: Option<Preset> = None;
+
     // the last tick; used for calculating dt
     let mut last_tick: Instant = Instant::now();
 
@@ -47,7 +272,33 @@ fn main() {
 
     // balls with gravity
     //let mut objects: Vec<Object> = vec![];
-    let mut solver = Solver::new(10.0, w - 10.0, 10.0, h - 10.0);
+    let mut solver = Solver::new(0.0, w, 0.0, h);
+    if let Some((playing, _)) = &playback {
+        solver = playing.initial.to_solver();
+    } else if scene_flag {
+        let scene = load_scene(&scene_path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        solver = scene.to_solver();
+    }
+
+    // whether the bounds follow the size of the window when it is resized; scenes bring their own bounds, and
+    // `--fixed-bounds` keeps the starting ones
+    let mut follow_window: bool =
+        !scene_flag && playback.is_none() && !args.iter().any(|a| a == "--fixed-bounds");
+
+    // integrators to cycle through, starting with the solver's default
+    let integrators: [Arc<dyn Integrator>; 4] = [
+        Arc::new(SemiImplicitEuler),
+        Arc::new(VelocityVerlet),
+        Arc::new(Leapfrog),
+        Arc::new(RungeKutta4),
+    ];
+    let mut integrator_index: usize = integrators
+        .iter()
+        .position(|i| i.name() == solver.integrator.name())
+        .unwrap_or(0);
 
     while let Some(event) = window.next() {
         // program loop
@@ -57,51 +308,449 @@ fn main() {
         let dt: f64 = last_tick.elapsed().as_secs_f64() * time_scaling_factor;
         last_tick = Instant::now();
 
-        if let Event::Input(input, _) = &event {
+        // input is ignored while playing a recording back
+        if let (Event::Input(input, _), None) = (&event, &playback) {
             // handle events
+            if let Input::Resize(args) = *input {
+                // move the bottom right corner of the bounds along with the window
+                if follow_window {
+                    let [width, height] = args.window_size;
+                    let bounds = SceneBounds {
+                        left: solver.bound_left,
+                        right: solver.bound_left + width,
+                        top: solver.bound_top,
+                        bottom: solver.bound_top + height,
+                    };
+                    apply(
+                        replay::Event::SetBounds { bounds },
+                        &mut solver,
+                        &mut recording,
+                    );
+                }
+            }
             if let Input::Move(x) = *input {
                 if let MouseCursor(pos) = x {
+                    if panning {
+                        camera.pan(vec2!(pos[0] - mouse_x, pos[1] - mouse_y));
+                    }
                     [mouse_x, mouse_y] = pos;
                 } else if let MouseScroll([_, y]) = x {
-                    if time_scaling_factor > 0.0 {
+                    if ctrl_down {
+                        // zoom in and out around the mouse
+                        camera.zoom_at(vec2!(mouse_x, mouse_y), ZOOM_STEP.powf(y));
+                    } else if time_scaling_factor > 0.0 {
                         time_scaling_factor += y / 100.0;
+
+                        let time_scale = time_scaling_factor;
+                        apply(
+                            replay::Event::SetTimeScale { time_scale },
+                            &mut solver,
+                            &mut recording,
+                        );
                     }
                 }
             }
             if let Input::Button(x) = *input {
                 if x.button == ButtonType::Mouse(MouseButton::Left) {
-                    // mouse left click
+                    // mouse left click; with Shift held, drags a spring from one ball to another instead, and with
+                    // Ctrl held, drags a ball around, or pins or unpins it when just clicked
+                    let position = camera.to_world(vec2!(mouse_x, mouse_y));
                     if x.state == ButtonState::Press {
-                        mouse_down_position = Some(Vec2::from_arr([mouse_x, mouse_y]));
+                        if ctrl_down {
+                            let picked = solver.object_at(position).and_then(|id| {
+                                Some((id, solver.objects.index_of(id)?, solver.get(id)?.body_type))
+                            });
+                            if let Some((id, object, body_type)) = picked {
+                                grabbed = Some((id, body_type, position));
+                                apply(
+                                    replay::Event::SetBodyType {
+                                        object,
+                                        body_type: BodyType::Kinematic,
+                                    },
+                                    &mut solver,
+                                    &mut recording,
+                                );
+                            }
+                        } else if shift_down {
+                            spring_start = solver.object_at(position);
+                        } else {
+                            mouse_down_position = Some(position);
+                        }
                     }
 
                     if x.state == ButtonState::Release {
-                        mouse_up_position = Some(Vec2::from_arr([mouse_x, mouse_y]));
+                        if let Some((id, body_type, start)) = grabbed.take() {
+                            // a click toggles between pinned and free; a drag lets go of the ball as it was
+                            let body_type = if Vec2::dist_scalar(start, position) > CLICK_DISTANCE {
+                                body_type
+                            } else if body_type == BodyType::Static {
+                                BodyType::Dynamic
+                            } else {
+                                BodyType::Static
+                            };
+                            if let Some(object) = solver.objects.index_of(id) {
+                                apply(
+                                    replay::Event::SetBodyType { object, body_type },
+                                    &mut solver,
+                                    &mut recording,
+                                );
+                            }
+                        } else if let Some(start) = spring_start.take() {
+                            let end = solver.object_at(position).filter(|&end| end != start);
+                            if let (Some(a), Some(b)) = (
+                                solver.objects.index_of(start),
+                                end.and_then(|end| solver.objects.index_of(end)),
+                            ) {
+                                let rest_length = Vec2::dist_scalar(
+                                    solver.objects[a].position,
+                                    solver.objects[b].position,
+                                );
+                                let link = Link::Spring {
+                                    rest_length,
+                                    stiffness: SPRING_STIFFNESS,
+                                    damping: SPRING_DAMPING,
+                                };
+                                apply(
+                                    replay::Event::AddConstraint {
+                                        constraint: SceneConstraint { a, b, link },
+                                    },
+                                    &mut solver,
+                                    &mut recording,
+                                );
+                            }
+                        } else if mouse_down_position.is_some() {
+                            mouse_up_position = Some(position);
+                        }
+                    }
+                } else if x.button == ButtonType::Mouse(MouseButton::Right) {
+                    // right drag draws a wall
+                    let position = camera.to_world(vec2!(mouse_x, mouse_y));
+                    if x.state == ButtonState::Press {
+                        wall_start_position = Some(position);
+                    } else if let Some(start) = wall_start_position.take() {
+                        if Vec2::dist_scalar(start, position) >= MIN_WALL_LENGTH {
+                            let collider = Collider::Segment(Segment::from((start, position)));
+                            apply(
+                                replay::Event::AddCollider { collider },
+                                &mut solver,
+                                &mut recording,
+                            );
+                        }
+                    }
+                } else if x.button == ButtonType::Mouse(MouseButton::Middle) {
+                    // middle drag pans the camera
+                    panning = x.state == ButtonState::Press;
+                } else if x.button == ButtonType::Keyboard(Key::C) {
+                    // back to the default camera
+                    if x.state == ButtonState::Press {
+                        camera = Camera::default();
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::F) {
+                    // toggle whether the bounds follow the window size; takes effect on the next resize
+                    if x.state == ButtonState::Press {
+                        follow_window = !follow_window;
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::LCtrl)
+                    || x.button == ButtonType::Keyboard(Key::RCtrl)
+                {
+                    ctrl_down = x.state == ButtonState::Press;
+                } else if x.button == ButtonType::Keyboard(Key::LShift)
+                    || x.button == ButtonType::Keyboard(Key::RShift)
+                {
+                    shift_down = x.state == ButtonState::Press;
+                } else if x.button == ButtonType::Keyboard(Key::S) && ctrl_down {
+                    // save the scene
+                    if x.state == ButtonState::Press {
+                        let json = Scene::from_solver(&solver).to_json();
+                        if let Err(e) = fs::write(&scene_path, json) {
+                            eprintln!("could not save {}: {}", scene_path, e);
+                        }
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::O) && ctrl_down {
+                    // load the scene, replacing everything
+                    if x.state == ButtonState::Press {
+                        match load_scene(&scene_path) {
+                            Ok(scene) => {
+                                apply(
+                                    replay::Event::LoadScene {
+                                        scene: Box::new(scene),
+                                    },
+                                    &mut solver,
+                                    &mut recording,
+                                );
+                                integrator_index = integrators
+                                    .iter()
+                                    .position(|i| i.name() == solver.integrator.name())
+                                    .unwrap_or(0);
+                            }
+                            Err(e) => eprintln!("{}", e),
+                        }
                     }
                 } else if x.button == ButtonType::Keyboard(Key::Backspace)
                     || x.button == ButtonType::Keyboard(Key::Delete)
                 {
                     // clear objects with backspace/delete and reset time scaling factor
-                    solver.objects = vec![];
-                    time_scaling_factor = 1.0;
+                    if x.state == ButtonState::Press {
+                        time_scaling_factor = 1.0;
+                        apply(replay::Event::Clear, &mut solver, &mut recording);
+                        let time_scale = time_scaling_factor;
+                        apply(
+                            replay::Event::SetTimeScale { time_scale },
+                            &mut solver,
+                            &mut recording,
+                        );
+                    }
                 } else if x.button == ButtonType::Keyboard(Key::Space) {
                     // space toggle vectors
                     if x.state == ButtonState::Press {
-                        show_vectors = !show_vectors
+                        show_vectors = !show_vectors;
+
+                        let option = "vectors".to_string();
+                        apply(
+                            replay::Event::ToggleDisplay { option },
+                            &mut solver,
+                            &mut recording,
+                        );
                     }
                 } else if x.button == ButtonType::Keyboard(Key::P) {
                     // toggle show positions
                     if x.state == ButtonState::Press {
                         show_predictions = !show_predictions;
+
+                        let option = "predictions".to_string();
+                        apply(
+                            replay::Event::ToggleDisplay { option },
+                            &mut solver,
+                            &mut recording,
+                        );
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::H) {
+                    // toggle the diagnostics HUD
+                    if x.state == ButtonState::Press {
+                        show_hud = !show_hud;
+
+                        let option = "hud".to_string();
+                        apply(
+                            replay::Event::ToggleDisplay { option },
+                            &mut solver,
+                            &mut recording,
+                        );
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::I) {
+                    // cycle through the integrators, showing the current one in the title bar
+                    if x.state == ButtonState::Press {
+                        integrator_index = (integrator_index + 1) % integrators.len();
+                        let name = integrators[integrator_index].name().to_string();
+                        apply(
+                            replay::Event::SetIntegrator { name },
+                            &mut solver,
+                            &mut recording,
+                        );
+                        window.set_title(format!("gravitati - {}", solver.integrator.name()));
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::G) {
+                    // cycle between a uniform gravitational field, mutual gravitation and its Barnes–Hut
+                    // approximation
+                    if x.state == ButtonState::Press {
+                        let force_model = match solver.force_model {
+                            ForceModel::UniformField => ForceModel::mutual(),
+                            ForceModel::Mutual { .. } => ForceModel::barnes_hut(),
+                            ForceModel::BarnesHut { .. } => ForceModel::UniformField,
+                        };
+                        apply(
+                            replay::Event::SetForceModel { force_model },
+                            &mut solver,
+                            &mut recording,
+                        );
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::B) {
+                    // cycle every side of the window through bouncing, wrapping around, open and absorbing
+                    if x.state == ButtonState::Press {
+                        let next = BoundaryMode::ALL
+                            .iter()
+                            .position(|&i| i == solver.boundaries.bottom)
+                            .map_or(0, |i| (i + 1) % BoundaryMode::ALL.len());
+                        let boundaries = Boundaries::all(BoundaryMode::ALL[next]);
+                        apply(
+                            replay::Event::SetBoundaries { boundaries },
+                            &mut solver,
+                            &mut recording,
+                        );
+                        window.set_title(format!(
+                            "gravitati - {} walls",
+                            BoundaryMode::ALL[next].name()
+                        ));
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::M) {
+                    // cycle what a left drag throws between a ball and each preset
+                    if x.state == ButtonState::Press {
+                        spawn_preset = match spawn_preset {
+                            None => presets.first().copied(),
+                            Some(current) => presets
+                                .iter()
+                                .position(|&i| i == current)
+                                .and_then(|i| presets.get(i + 1))
+                                .copied(),
+                        };
+                        window.set_title(format!(
+                            "gravitati - throwing {}",
+                            spawn_preset.map_or("balls", |i| i.name())
+                        ));
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::R) {
+                    // start or stop recording
+                    if x.state == ButtonState::Press {
+                        match recording.take() {
+                            None => {
+                                recording = Some(Recording::start(&mut solver));
+                                window.set_title("gravitati - recording".to_string());
+                            }
+                            Some(mut finished) => {
+                                finished.finish(&solver);
+                                if let Err(e) = fs::write(&recording_path, finished.to_json()) {
+                                    eprintln!("could not save {}: {}", recording_path, e);
+                                }
+                                window.set_title("gravitati".to_string());
+                            }
+                        }
+                    }
+                } else if x.button == ButtonType::Keyboard(Key::E) {
+                    // start or stop exporting trajectories
+                    if x.state == ButtonState::Press {
+                        match export.take() {
+                            None => match create_exporter(&export_path) {
+                                Ok(exporter) => export = Some((exporter, 0)),
+                                Err(e) => eprintln!("{}", e),
+                            },
+                            Some((mut exporter, _)) => {
+                                if let Err(e) = exporter.finish() {
+                                    eprintln!("could not export to {}: {}", export_path, e);
+                                }
+                            }
+                        }
                     }
                 }
             }
         }
 
-        solver.solve_all(dt);
+        // simulated time before this frame's step, to tell whether the HUD needs a new snapshot
+        let time_before_step = solver.time;
+
+        // write every fixed step taken this frame to the export, stopping it (and saving what it has) if that fails
+        let mut export_step = |solver: &Solver| {
+            if let Some((exporter, steps)) = &mut export {
+                if let Err(e) = exporter.write_step(*steps, solver) {
+                    eprintln!("could not export to {}: {}", export_path, e);
+                    if let Err(e) = exporter.finish() {
+                        eprintln!("could not export to {}: {}", export_path, e);
+                    }
+                    export = None;
+                } else {
+                    *steps += 1;
+                }
+            }
+        };
+
+        // step the physics at a fixed rate, and draw objects partway between their last two states
+        let alpha = match &mut playback {
+            None => {
+                if let Some(recording) = &mut recording {
+                    recording.step(dt);
+                }
+                solver.step_with(dt, &mut export_step)
+            }
+            Some((playing, cursor)) => {
+                // play the timeline up to and including the next step
+                let mut alpha = None;
+                while let (None, Some(entry)) = (alpha, playing.timeline.get(*cursor)) {
+                    if let Entry::Event { event } = entry {
+                        match event {
+                            replay::Event::SetTimeScale { time_scale } => {
+                                time_scaling_factor = *time_scale
+                            }
+                            replay::Event::ToggleDisplay { option } if option == "vectors" => {
+                                show_vectors = !show_vectors
+                            }
+                            replay::Event::ToggleDisplay { option } if option == "predictions" => {
+                                show_predictions = !show_predictions
+                            }
+                            replay::Event::ToggleDisplay { option } if option == "hud" => {
+                                show_hud = !show_hud
+                            }
+                            _ => {}
+                        }
+                        event.apply(&mut solver);
+                    } else if let Entry::Step { dt } = entry {
+                        alpha = Some(solver.step_with(*dt, &mut export_step));
+                    }
+                    *cursor += 1;
+                }
+
+                if *cursor == playing.timeline.len() {
+                    // the end of the recording: check we ended up in exactly the same state
+                    let (expected, actual) = (playing.final_hash, solver.state_hash());
+                    if expected == actual {
+                        println!("replay matched state hash {:016x}", actual);
+                    } else {
+                        eprintln!(
+                            "replay diverged: expected state hash {:016x}, got {:016x}",
+                            expected, actual
+                        );
+                    }
+                    playback = None;
+                }
+
+                alpha.unwrap_or_else(|| solver.step(0.0))
+            }
+        };
+
+        // take a snapshot for the HUD every step, starting over when the totals stop being comparable
+        if show_hud {
+            let started = (
+                solver.objects.len(),
+                solver.force_model,
+                solver.integrator.name(),
+            );
+            if started != history_started {
+                history.clear();
+                history_started = started;
+            }
+            if solver.time != time_before_step || history.reference().is_none() {
+                history.push(solver.diagnostics());
+            }
+        }
+
+        // steer the ball being dragged towards the mouse
+        if let Some((id, _, _)) = grabbed {
+            let mouse = camera.to_world(vec2!(mouse_x, mouse_y));
+            if let (Some(object), Some(i)) = (solver.objects.index_of(id), solver.get(id)) {
+                let velocity = (mouse - i.position) * GRAB_SPEED;
+                apply(
+                    replay::Event::SetVelocity { object, velocity },
+                    &mut solver,
+                    &mut recording,
+                );
+            }
+        }
+
         // check if the user created a object and actually create it
         if let [Some(d), Some(u)] = [mouse_down_position, mouse_up_position] {
-            solver.add_object(d, (u - d) * 2.0, 10.0);
+            if let Some(preset) = spawn_preset {
+                apply(
+                    replay::Event::SpawnPreset {
+                        preset,
+                        position: d,
+                        velocity: (u - d) * 2.0,
+                    },
+                    &mut solver,
+                    &mut recording,
+                );
+            } else {
+                let color = BALL_COLORS[solver.objects.len() % BALL_COLORS.len()];
+                let object =
+                    SceneObject::from(&Object::new(d, (u - d) * 2.0, 10.0).with_color(color));
+                apply(replay::Event::Spawn { object }, &mut solver, &mut recording);
+            }
 
             // reset
             mouse_down_position = None;
@@ -109,21 +758,168 @@ fn main() {
         }
 
         // drawers for different types of things
-        let ellipse_drawer = Ellipse::new([1.0; 4]);
         let ellipse2_drawer = Ellipse::new([0.5; 4]);
         let ellipse3_drawer = Ellipse::new([0.5, 0.5, 0.5, 0.25]);
         let line_drawer = Line::new([1.0, 1.0, 1.0, 0.25], 1.0);
+        // the mouse in world coordinates, for previews
+        let mouse = camera.to_world(vec2!(mouse_x, mouse_y));
+
         window.draw_2d(&event, |context, graphics, _device| {
             // background
             clear([0.0; 4], graphics);
 
+            // everything but the HUD is drawn in world coordinates
+            let transform = camera.transform(context.transform);
+
+            // outline the bounds, which only lie inside the window once the camera has moved
+            Rectangle::new_border(BOUNDS_COLOR, 1.0 / camera.zoom).draw(
+                [
+                    solver.bound_left,
+                    solver.bound_top,
+                    solver.bound_right - solver.bound_left,
+                    solver.bound_bottom - solver.bound_top,
+                ],
+                &context.draw_state,
+                transform,
+                graphics,
+            );
+
+            // draw each static collider
+            for collider in &solver.colliders {
+                match collider {
+                    Collider::Segment(segment) => {
+                        Line::new(COLLIDER_COLOR, 1.5).draw(
+                            segment.as_4_f64_arr(),
+                            &context.draw_state,
+                            transform,
+                            graphics,
+                        );
+                    }
+                    Collider::Polygon { points } => {
+                        let corners: Vec<[f64; 2]> = points.iter().map(|i| [i.x, i.y]).collect();
+                        Polygon::new(COLLIDER_COLOR).draw(
+                            &corners,
+                            &context.draw_state,
+                            transform,
+                            graphics,
+                        );
+                    }
+                }
+            }
+
+            // the wall being drawn
+            if let Some(start) = wall_start_position {
+                Line::new(
+                    [COLLIDER_COLOR[0], COLLIDER_COLOR[1], COLLIDER_COLOR[2], 0.5],
+                    1.5,
+                )
+                .draw(
+                    [start.x, start.y, mouse.x, mouse.y],
+                    &context.draw_state,
+                    transform,
+                    graphics,
+                );
+            }
+
+            // fill in each soft body, in the color of its balls
+            for body in &solver.soft_bodies {
+                let corners: Vec<[f64; 2]> = body
+                    .ring
+                    .iter()
+                    .filter_map(|&id| solver.get(id))
+                    .map(|i| {
+                        let position = i.interpolated_position(alpha);
+                        [position.x, position.y]
+                    })
+                    .collect();
+                if let Some(first) = body.ring.first().and_then(|&id| solver.get(id)) {
+                    let color = first.color;
+                    Polygon::new([color[0], color[1], color[2], SOFT_BODY_ALPHA]).draw(
+                        &corners,
+                        &context.draw_state,
+                        transform,
+                        graphics,
+                    );
+                }
+            }
+
+            // draw each link between balls
+            for constraint in &solver.constraints {
+                if let (Some(a), Some(b)) = (solver.get(constraint.a), solver.get(constraint.b)) {
+                    let (a, b) = (
+                        a.interpolated_position(alpha),
+                        b.interpolated_position(alpha),
+                    );
+                    Line::new(CONSTRAINT_COLOR, 1.0).draw(
+                        [a.x, a.y, b.x, b.y],
+                        &context.draw_state,
+                        transform,
+                        graphics,
+                    );
+                }
+            }
+
+            // the spring being dragged
+            if let Some(start) = spring_start.and_then(|start| solver.get(start)) {
+                Line::new(
+                    [
+                        CONSTRAINT_COLOR[0],
+                        CONSTRAINT_COLOR[1],
+                        CONSTRAINT_COLOR[2],
+                        0.4,
+                    ],
+                    1.0,
+                )
+                .draw(
+                    [start.position.x, start.position.y, mouse.x, mouse.y],
+                    &context.draw_state,
+                    transform,
+                    graphics,
+                );
+            }
+
             // draw each object
             for i in &solver.objects {
-                // draw ball
-                ellipse_drawer.draw(
-                    circle(i.position.x, i.position.y - 10.0, BALL_RADIUS),
+                let position = i.interpolated_position(alpha);
+
+                // draw ball, faded out while it sleeps
+                let mut color = i.color;
+                if i.asleep {
+                    color[3] *= SLEEPING_ALPHA;
+                }
+                Ellipse::new(color).draw(
+                    circle(position.x, position.y, i.radius),
                     &context.draw_state,
-                    context.transform,
+                    transform,
+                    graphics,
+                );
+
+                // ring static and kinematic balls
+                let ring = match i.body_type {
+                    BodyType::Dynamic => None,
+                    BodyType::Kinematic => Some(KINEMATIC_COLOR),
+                    BodyType::Static => Some(STATIC_COLOR),
+                };
+                if let Some(ring) = ring {
+                    Ellipse::new_border(ring, 1.0 / camera.zoom).draw(
+                        circle(position.x, position.y, i.radius),
+                        &context.draw_state,
+                        transform,
+                        graphics,
+                    );
+                }
+
+                // draw a radius so the ball's rotation shows
+                let angle = i.interpolated_angle(alpha);
+                Line::new(MARKER_COLOR, 1.0).draw(
+                    [
+                        position.x,
+                        position.y,
+                        position.x + angle.cos() * i.radius,
+                        position.y + angle.sin() * i.radius,
+                    ],
+                    &context.draw_state,
+                    transform,
                     graphics,
                 );
 
@@ -131,32 +927,73 @@ fn main() {
                     // draw the vector arrow if show_vectors
                     line_drawer.draw_arrow(
                         [
-                            i.position.x,
-                            i.position.y - 10.0,
-                            i.position.x + i.velocity.x / 3.0,
-                            i.position.y - 10.0 + i.velocity.y / 3.0 + i.velocity.y.signum() * 10.0,
+                            position.x,
+                            position.y,
+                            position.x + i.velocity.x / 3.0,
+                            position.y + i.velocity.y / 3.0 + i.velocity.y.signum() * 10.0,
                         ],
                         6.0,
                         &context.draw_state,
-                        context.transform,
+                        transform,
                         graphics,
                     );
                 }
             }
 
+            if show_hud {
+                // plot the drift of energy, momentum and angular momentum since the first snapshot, scaled so the
+                // largest fits, around a line through zero
+                let drifts = history.drifts();
+                let largest = drifts
+                    .iter()
+                    .flat_map(|d| [d.energy, d.momentum, d.angular_momentum])
+                    .fold(1e-6, |largest: f64, i| largest.max(i.abs()));
+                let x = |idx: usize| 10.0 + HUD_WIDTH * idx as f64 / (HUD_SAMPLES - 1) as f64;
+                let y = |drift: f64| 10.0 + HUD_HEIGHT / 2.0 * (1.0 - drift / largest);
+
+                Rectangle::new([0.1, 0.1, 0.1, 0.75]).draw(
+                    [10.0, 10.0, HUD_WIDTH, HUD_HEIGHT],
+                    &context.draw_state,
+                    context.transform,
+                    graphics,
+                );
+                line_drawer.draw(
+                    [10.0, y(0.0), 10.0 + HUD_WIDTH, y(0.0)],
+                    &context.draw_state,
+                    context.transform,
+                    graphics,
+                );
+
+                for (idx, pair) in drifts.windows(2).enumerate() {
+                    let lines = [
+                        (pair[0].energy, pair[1].energy),
+                        (pair[0].momentum, pair[1].momentum),
+                        (pair[0].angular_momentum, pair[1].angular_momentum),
+                    ];
+                    for ((from, to), color) in lines.into_iter().zip(HUD_COLORS) {
+                        Line::new(color, 1.0).draw(
+                            [x(idx), y(from), x(idx + 1), y(to)],
+                            &context.draw_state,
+                            context.transform,
+                            graphics,
+                        );
+                    }
+                }
+            }
+
             if let [Some(x), None] = [mouse_down_position, mouse_up_position] {
                 ellipse2_drawer.draw(
-                    circle(x.x, x.y, 10.0),
+                    circle(x.x, x.y, OBJECT_RADIUS),
                     &context.draw_state,
-                    context.transform,
+                    transform,
                     graphics,
                 );
 
                 line_drawer.draw_arrow(
-                    [x.x, x.y, mouse_x, mouse_y],
+                    [x.x, x.y, mouse.x, mouse.y],
                     6.0,
                     &context.draw_state,
-                    context.transform,
+                    transform,
                     graphics,
                 );
 
@@ -166,7 +1003,7 @@ fn main() {
                         .extend(10.0)
                         .as_4_f64_arr(),
                     &context.draw_state,
-                    context.transform,
+                    transform,
                     graphics,
                 );
                 */
@@ -176,20 +1013,27 @@ fn main() {
                 } // prevent showing predictions when show_predictions != true
 
                 let position = vec2!(x.x, x.y);
-                let possible_up = vec2!(mouse_x, mouse_y);
+                let possible_up = mouse;
                 let velocity = (possible_up - position) * 2.0;
 
-                let positions = solver.solve_for_x_seconds(position, velocity, 1.0, 4);
+                let positions = solver.predict(Object::new(position, velocity, 10.0), 1.0, 0.025);
 
                 for i in positions {
                     ellipse3_drawer.draw(
                         circle(i.x, i.y, 5.0),
                         &context.draw_state,
-                        context.transform,
+                        transform,
                         graphics,
                     );
                 }
             }
         }); // window.draw2d
     } // while let
+
+    // finish an export still running when the window closes
+    if let Some((mut exporter, _)) = export {
+        if let Err(e) = exporter.finish() {
+            eprintln!("could not export to {}: {}", export_path, e);
+        }
+    }
 } // fn main
@@ -0,0 +1,267 @@
+use std::ops::{Deref, DerefMut};
+
+/// Stable handle to an object in a `Solver`, returned by `Solver::add`.
+///
+/// Unlike an index into `Solver::objects`, an id keeps referring to the same object when others are removed, and
+/// never refers to a different object once its own has been removed, even if the slot is reused.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId {
+    slot: u32,
+    generation: u32,
+}
+
+impl ObjectId {
+    /// The id packed into a single number, e.g. to write to a file. Ids handed out before anything is removed are
+    /// numbered `0, 1, 2, ...`.
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.slot as u64
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    /// Incremented every time the slot is freed, so old ids stop matching. A slot freed at `u32::MAX` is retired
+    /// for good rather than wrapping around to ids that were handed out before.
+    generation: u32,
+
+    /// Where the value is in the dense storage, or `None` if the slot is free.
+    index: Option<usize>,
+}
+
+/// Generational arena: values stored contiguously, addressed by `ObjectId`s that survive removals.
+///
+/// Dereferences to a slice of the values, so everything that works on `&[T]` (forces, integrators, broad phases)
+/// keeps working on the dense storage directly. Removing a value moves the last one into its place, so positions in
+/// the slice aren't stable: hold on to ids instead.
+#[derive(Debug, Clone)]
+pub struct Arena<T> {
+    values: Vec<T>,
+
+    /// The id of the value at the same position in `values`.
+    ids: Vec<ObjectId>,
+
+    slots: Vec<Slot>,
+
+    /// Slots free to be reused.
+    free: Vec<u32>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Arena<T> {
+        Arena::new()
+    }
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Arena<T> {
+        Arena {
+            values: vec![],
+            ids: vec![],
+            slots: vec![],
+            free: vec![],
+        }
+    }
+
+    /// Stores `value` at the end of the slice and returns its id.
+    pub fn insert(&mut self, value: T) -> ObjectId {
+        let index = Some(self.values.len());
+        let id = match self.free.pop() {
+            Some(slot) => {
+                let entry = &mut self.slots[slot as usize];
+                entry.index = index;
+                ObjectId {
+                    slot,
+                    generation: entry.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    index,
+                });
+                ObjectId {
+                    slot: (self.slots.len() - 1) as u32,
+                    generation: 0,
+                }
+            }
+        };
+
+        self.values.push(value);
+        self.ids.push(id);
+        id
+    }
+
+    /// Removes and returns the value with id `id`, or `None` if it was already removed.
+    pub fn remove(&mut self, id: ObjectId) -> Option<T> {
+        let index = self.index_of(id)?;
+
+        self.free_slot(id.slot);
+
+        // the last value takes the removed one's place
+        self.ids.swap_remove(index);
+        if let Some(moved) = self.ids.get(index) {
+            self.slots[moved.slot as usize].index = Some(index);
+        }
+        Some(self.values.swap_remove(index))
+    }
+
+    /// Removes every value. Ids handed out so far stop matching.
+    pub fn clear(&mut self) {
+        for id in std::mem::take(&mut self.ids) {
+            self.free_slot(id.slot);
+        }
+        self.values.clear();
+    }
+
+    /// Empties `slot` so the ids handed out for it stop matching, and makes it free to reuse unless its generations
+    /// have run out.
+    fn free_slot(&mut self, slot: u32) {
+        let entry = &mut self.slots[slot as usize];
+        entry.index = None;
+        if let Some(generation) = entry.generation.checked_add(1) {
+            entry.generation = generation;
+            self.free.push(slot);
+        }
+    }
+
+    pub fn get(&self, id: ObjectId) -> Option<&T> {
+        self.index_of(id).map(|index| &self.values[index])
+    }
+
+    pub fn get_mut(&mut self, id: ObjectId) -> Option<&mut T> {
+        self.index_of(id).map(|index| &mut self.values[index])
+    }
+
+    pub fn contains(&self, id: ObjectId) -> bool {
+        self.index_of(id).is_some()
+    }
+
+    /// Current position of the value with id `id` in the slice.
+    pub fn index_of(&self, id: ObjectId) -> Option<usize> {
+        match self.slots.get(id.slot as usize) {
+            Some(entry) if entry.generation == id.generation => entry.index,
+            _ => None,
+        }
+    }
+
+    /// Id of the value at position `index` in the slice.
+    pub fn id_at(&self, index: usize) -> Option<ObjectId> {
+        self.ids.get(index).copied()
+    }
+
+    /// Ids of every value, in the same order as the slice.
+    pub fn ids(&self) -> &[ObjectId] {
+        &self.ids
+    }
+
+    /// Every value with its id, in the same order as the slice.
+    pub fn entries(&self) -> impl Iterator<Item = (ObjectId, &T)> {
+        self.ids.iter().copied().zip(self.values.iter())
+    }
+
+    /// Every value with its id, mutably, in the same order as the slice.
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = (ObjectId, &mut T)> {
+        self.ids.iter().copied().zip(self.values.iter_mut())
+    }
+}
+
+impl<T> Deref for Arena<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.values
+    }
+}
+
+impl<T> DerefMut for Arena<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Arena<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Arena<T> {
+    type Item = &'a mut T;
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_ids_stay_stale_when_the_slot_is_reused() {
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+        arena.remove(a);
+        let b = arena.insert("b");
+
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena.remove(a), None);
+        assert_eq!(arena.get(b), Some(&"b"));
+    }
+
+    #[test]
+    fn removing_moves_the_last_value_into_place() {
+        let mut arena = Arena::new();
+        let [a, b, c] = ["a", "b", "c"].map(|value| arena.insert(value));
+
+        assert_eq!(arena.remove(a), Some("a"));
+        assert_eq!(&arena[..], &["c", "b"]);
+        assert_eq!(arena.index_of(c), Some(0));
+        assert_eq!(arena.index_of(b), Some(1));
+        assert_eq!(arena.ids(), &[c, b]);
+        assert_eq!(arena.get(c), Some(&"c"));
+    }
+
+    #[test]
+    fn clear_makes_every_id_stale() {
+        let mut arena = Arena::new();
+        let ids = ["a", "b", "c"].map(|value| arena.insert(value));
+        arena.clear();
+
+        assert!(arena.is_empty());
+        assert!(ids.iter().all(|&id| !arena.contains(id)));
+
+        let d = arena.insert("d");
+        assert!(ids.iter().all(|&id| !arena.contains(id)));
+        assert_eq!(arena.get(d), Some(&"d"));
+    }
+
+    #[test]
+    fn slots_are_retired_instead_of_wrapping_around() {
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+        arena.remove(a);
+        // as if the slot had already been reused all but once
+        arena.slots[a.slot as usize].generation = u32::MAX;
+        let last = arena.insert("last");
+        assert_eq!(last.slot, a.slot);
+        assert_eq!(last.generation, u32::MAX);
+
+        // the slot is used up, so the next value gets a new one and no old id can match again
+        arena.remove(last);
+        let next = arena.insert("next");
+        assert_ne!(next.slot, a.slot);
+        assert_eq!(arena.get(last), None);
+        assert_eq!(
+            arena.get(ObjectId {
+                slot: a.slot,
+                generation: 0
+            }),
+            None
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// What happens to an object that reaches one side of the solver's bounds.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BoundaryMode {
+    /// Objects bounce back, losing some speed to the wall's restitution and friction.
+    #[default]
+    Bounce,
+
+    /// Objects that cross the side come back in through the opposite side (periodic, or toroidal, boundaries).
+    /// Objects near the side also collide with objects near the opposite side.
+    Wrap,
+
+    /// Objects leave freely, and are removed once they are entirely outside.
+    Open,
+
+    /// Objects are removed as soon as they touch the side.
+    Absorb,
+}
+
+impl BoundaryMode {
+    /// Every mode, in the order the app cycles through them.
+    pub const ALL: [BoundaryMode; 4] = [
+        BoundaryMode::Bounce,
+        BoundaryMode::Wrap,
+        BoundaryMode::Open,
+        BoundaryMode::Absorb,
+    ];
+
+    /// Name of the mode, as written in scene files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoundaryMode::Bounce => "bounce",
+            BoundaryMode::Wrap => "wrap",
+            BoundaryMode::Open => "open",
+            BoundaryMode::Absorb => "absorb",
+        }
+    }
+}
+
+/// The mode of each side of the solver's bounds.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Boundaries {
+    pub left: BoundaryMode,
+    pub right: BoundaryMode,
+    pub top: BoundaryMode,
+    pub bottom: BoundaryMode,
+}
+
+impl Boundaries {
+    /// Every side in the same mode.
+    pub fn all(mode: BoundaryMode) -> Boundaries {
+        Boundaries {
+            left: mode,
+            right: mode,
+            top: mode,
+            bottom: mode,
+        }
+    }
+
+    /// Whether objects can wrap around horizontally, through either the left or the right side.
+    pub fn wraps_x(&self) -> bool {
+        self.left == BoundaryMode::Wrap || self.right == BoundaryMode::Wrap
+    }
+
+    /// Whether objects can wrap around vertically, through either the top or the bottom side.
+    pub fn wraps_y(&self) -> bool {
+        self.top == BoundaryMode::Wrap || self.bottom == BoundaryMode::Wrap
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::object::Object;
+use super::vec2::Vec2;
+
+/// Smallest `cell_size` of a `BroadPhase::UniformGrid`. Smaller cells would have every object spread over so many
+/// of them that bucketing the objects would take forever.
+/// Measured in pixels
+pub const MIN_CELL_SIZE: f64 = 1.0;
+
+/// Broad-phase collision detection strategy.
+///
+/// The broad phase cheaply narrows down which pairs of objects could possibly touch within the next step, so that
+/// the (much more expensive) swept circle test only runs on those pairs.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum BroadPhase {
+    /// Tests every pair of objects against each other. O(n²).
+    BruteForce,
+
+    /// Buckets objects into a uniform grid of square cells (a spatial hash) and only pairs up objects sharing a
+    /// cell. Works best when `cell_size` is a little larger than the distance an object covers in a step.
+    ///
+    /// A `cell_size` that isn't finite or is smaller than `MIN_CELL_SIZE` falls back to `BruteForce`.
+    UniformGrid { cell_size: f64 },
+
+    /// Sorts objects along the x axis and sweeps over them, only pairing up objects whose extents overlap.
+    #[default]
+    SortAndSweep,
+}
+
+/// Axis-aligned box containing an object over a whole step.
+#[derive(Debug, Copy, Clone)]
+struct Bounds {
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Bounds {
+    fn swept(object: &Object) -> Bounds {
+        let (a, b, radius) = (object.position, object.next_position, object.radius);
+
+        Bounds {
+            min: Vec2 {
+                x: a.x.min(b.x) - radius,
+                y: a.y.min(b.y) - radius,
+            },
+            max: Vec2 {
+                x: a.x.max(b.x) + radius,
+                y: a.y.max(b.y) + radius,
+            },
+        }
+    }
+
+    fn overlaps(&self, other: &Bounds) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+}
+
+impl BroadPhase {
+    /// Whether the broad phase can be used as is: a uniform grid needs a finite `cell_size` of at least
+    /// `MIN_CELL_SIZE`.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            BroadPhase::UniformGrid { cell_size } => {
+                cell_size >= MIN_CELL_SIZE && cell_size.is_finite()
+            }
+            BroadPhase::BruteForce | BroadPhase::SortAndSweep => true,
+        }
+    }
+
+    /// Returns every pair of objects `(idx, jdx)` with `idx < jdx` whose swept bounding boxes overlap.
+    ///
+    /// Each pair is returned once, in ascending order.
+    pub fn candidate_pairs(&self, objects: &[Object]) -> Vec<(usize, usize)> {
+        let bounds: Vec<Bounds> = objects.iter().map(Bounds::swept).collect();
+
+        match *self {
+            BroadPhase::BruteForce => Self::brute_force(&bounds),
+            BroadPhase::UniformGrid { cell_size } if self.is_valid() => {
+                Self::uniform_grid(&bounds, cell_size)
+            }
+            // zero, negative or tiny cells would bucket every object into endless cells
+            BroadPhase::UniformGrid { .. } => Self::brute_force(&bounds),
+            BroadPhase::SortAndSweep => Self::sort_and_sweep(&bounds),
+        }
+    }
+
+    fn brute_force(bounds: &[Bounds]) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = vec![];
+        for (idx, i) in bounds.iter().enumerate() {
+            for (jdx, j) in bounds.iter().enumerate().skip(idx + 1) {
+                if i.overlaps(j) {
+                    pairs.push((idx, jdx));
+                }
+            }
+        }
+
+        pairs
+    }
+
+    fn uniform_grid(bounds: &[Bounds], cell_size: f64) -> Vec<(usize, usize)> {
+        let cell = |v: f64| (v / cell_size).floor() as i64;
+
+        // bucket every object into each cell its box covers
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (idx, i) in bounds.iter().enumerate() {
+            for x in cell(i.min.x)..=cell(i.max.x) {
+                for y in cell(i.min.y)..=cell(i.max.y) {
+                    cells.entry((x, y)).or_default().push(idx);
+                }
+            }
+        }
+
+        let mut pairs: Vec<(usize, usize)> = vec![];
+        for bucket in cells.values() {
+            for (n, &idx) in bucket.iter().enumerate() {
+                for &jdx in &bucket[n + 1..] {
+                    if bounds[idx].overlaps(&bounds[jdx]) {
+                        pairs.push((idx.min(jdx), idx.max(jdx)));
+                    }
+                }
+            }
+        }
+
+        // objects spanning several cells can be paired more than once
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    fn sort_and_sweep(bounds: &[Bounds]) -> Vec<(usize, usize)> {
+        let mut order: Vec<usize> = (0..bounds.len()).collect();
+        order.sort_unstable_by(|&a, &b| bounds[a].min.x.total_cmp(&bounds[b].min.x));
+
+        let mut pairs: Vec<(usize, usize)> = vec![];
+        let mut active: Vec<usize> = vec![];
+        for idx in order {
+            // drop everything that ends before this object starts
+            active.retain(|&jdx| bounds[jdx].max.x >= bounds[idx].min.x);
+
+            for &jdx in &active {
+                if bounds[idx].overlaps(&bounds[jdx]) {
+                    pairs.push((idx.min(jdx), idx.max(jdx)));
+                }
+            }
+
+            active.push(idx);
+        }
+
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reproducible crowd of `count` objects of varying size, moving in every direction at up to `speed` pixels
+    /// per step, inside an 800 by 800 box.
+    fn crowd(count: usize, speed: f64) -> Vec<Object> {
+        // xorshift, so the crowd is the same every run
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+
+        (0..count)
+            .map(|_| {
+                let position = Vec2 {
+                    x: 800.0 * random(),
+                    y: 800.0 * random(),
+                };
+                let displacement = Vec2 {
+                    x: speed * (2.0 * random() - 1.0),
+                    y: speed * (2.0 * random() - 1.0),
+                };
+                let mut object = Object::new(position, Vec2 { x: 0.0, y: 0.0 }, 10.0)
+                    .with_radius(2.0 + 8.0 * random());
+                object.next_position = position + displacement;
+                object
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_broad_phase_finds_the_same_pairs() {
+        let objects = crowd(2000, 30.0);
+        let pairs = BroadPhase::BruteForce.candidate_pairs(&objects);
+        assert!(!pairs.is_empty());
+
+        for broad_phase in [
+            BroadPhase::UniformGrid { cell_size: 40.0 },
+            BroadPhase::UniformGrid { cell_size: 7.0 },
+            BroadPhase::SortAndSweep,
+        ] {
+            assert_eq!(
+                broad_phase.candidate_pairs(&objects),
+                pairs,
+                "{:?}",
+                broad_phase
+            );
+        }
+    }
+
+    #[test]
+    fn uniform_grids_with_unusable_cells_fall_back_to_brute_force() {
+        let objects = crowd(200, 30.0);
+        let pairs = BroadPhase::BruteForce.candidate_pairs(&objects);
+
+        for cell_size in [0.0, -40.0, 1e-9, f64::NAN, f64::INFINITY] {
+            let broad_phase = BroadPhase::UniformGrid { cell_size };
+            assert!(!broad_phase.is_valid());
+            assert_eq!(
+                broad_phase.candidate_pairs(&objects),
+                pairs,
+                "{:?}",
+                broad_phase
+            );
+        }
+    }
+}
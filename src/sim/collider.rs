@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+
+use super::segment::Segment;
+use super::vec2::Vec2;
+
+/// A static obstacle objects bounce off, on top of the solver's bounds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Collider {
+    /// A wall with no thickness, solid from both sides.
+    Segment(Segment),
+
+    /// A solid convex polygon, with its corners in order (either way round).
+    Polygon { points: Vec<Vec2> },
+}
+
+/// Where an object ends up after touching a collider.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SurfaceContact {
+    /// Position of the object's centre, just touching the surface.
+    pub position: Vec2,
+
+    /// Unit vector pointing out of the surface, towards the object.
+    pub normal: Vec2,
+}
+
+impl Collider {
+    /// The collider's edges: the segment itself, or every side of the polygon.
+    pub fn edges(&self) -> Vec<Segment> {
+        match self {
+            Collider::Segment(segment) => vec![*segment],
+            Collider::Polygon { points } => (0..points.len())
+                .map(|idx| Segment::from((points[idx], points[(idx + 1) % points.len()])))
+                .collect(),
+        }
+    }
+
+    /// Whether objects can bounce off the collider: a segment needs two different corners, and a polygon at least
+    /// three, going round once in the same direction with no two edges in line.
+    pub fn is_valid(&self) -> bool {
+        let finite = |i: &Vec2| i.x.is_finite() && i.y.is_finite();
+        match self {
+            Collider::Segment(segment) => {
+                finite(&segment.point1)
+                    && finite(&segment.point2)
+                    && segment.point1 != segment.point2
+            }
+            Collider::Polygon { points } => points.iter().all(finite) && is_convex(points),
+        }
+    }
+
+    /// Checks a circle of `radius` moving from `start` to `end` over a step against the collider.
+    ///
+    /// Returns where the circle should end the step instead, if it ends up overlapping the collider or passes
+    /// straight through it. The circle is placed against the surface nearest `end`, on the side it came from.
+    pub fn contact(&self, start: Vec2, end: Vec2, radius: f64) -> Option<SurfaceContact> {
+        match self {
+            Collider::Segment(segment) => segment_contact(segment, start, end, radius),
+            Collider::Polygon { points } => {
+                polygon_contact(points, &self.edges(), start, end, radius)
+            }
+        }
+    }
+}
+
+/// Whether the corners of a polygon, in order, make a convex one.
+fn is_convex(points: &[Vec2]) -> bool {
+    if points.len() < 3 {
+        return false;
+    }
+
+    // how far the boundary turns at each corner, as the sine and cosine of the angle scaled by both edges
+    let turns: Vec<(f64, f64)> = (0..points.len())
+        .map(|idx| {
+            let corner = points[(idx + 1) % points.len()];
+            let (before, after) = (
+                corner - points[idx],
+                points[(idx + 2) % points.len()] - corner,
+            );
+            (before.cross(after), before.dot(after))
+        })
+        .collect();
+
+    // a star turns the same way at every corner too, but goes round more than once
+    let same_way =
+        turns.iter().all(|&(sin, _)| sin > 0.0) || turns.iter().all(|&(sin, _)| sin < 0.0);
+    let turned: f64 = turns.iter().map(|&(sin, cos)| sin.atan2(cos)).sum();
+    same_way && (turned.abs() - std::f64::consts::TAU).abs() < 1e-6
+}
+
+/// Unit normal of `segment`, on the same side as `point`.
+fn normal_towards(segment: &Segment, point: Vec2) -> Vec2 {
+    let direction = segment.point2 - segment.point1;
+    let normal = Vec2 {
+        x: -direction.y,
+        y: direction.x,
+    }
+    .normalize();
+
+    if normal.dot(point - segment.point1) < 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+fn segment_contact(
+    segment: &Segment,
+    start: Vec2,
+    end: Vec2,
+    radius: f64,
+) -> Option<SurfaceContact> {
+    let closest = segment.closest_point(end);
+    let offset = end - closest;
+    let path = Segment::from((start, end));
+
+    let normal = if Segment::intersection(&path, segment).is_some() {
+        // passed through the wall within the step: put it back on the side it started on
+        normal_towards(segment, start)
+    } else if offset.len() < radius {
+        if offset.len() != 0.0 {
+            offset.normalize()
+        } else {
+            normal_towards(segment, start)
+        }
+    } else {
+        return None;
+    };
+
+    Some(SurfaceContact {
+        position: closest + normal * radius,
+        normal,
+    })
+}
+
+fn polygon_contact(
+    points: &[Vec2],
+    edges: &[Segment],
+    start: Vec2,
+    end: Vec2,
+    radius: f64,
+) -> Option<SurfaceContact> {
+    if edges.is_empty() {
+        return None;
+    }
+
+    // corners are averaged to get a point inside the polygon, so every edge knows which way is out
+    let centre = points
+        .iter()
+        .fold(Vec2 { x: 0.0, y: 0.0 }, |sum, &i| sum + i)
+        * (1.0 / points.len() as f64);
+    let outward = |edge: &Segment| -normal_towards(edge, centre);
+    let inside = |point: Vec2| {
+        edges
+            .iter()
+            .all(|edge| outward(edge).dot(point - edge.point1) <= 0.0)
+    };
+    let nearest = |point: Vec2| {
+        edges
+            .iter()
+            .min_by(|a, b| {
+                let a = Vec2::dist_scalar(a.closest_point(point), point);
+                let b = Vec2::dist_scalar(b.closest_point(point), point);
+                a.total_cmp(&b)
+            })
+            .expect("polygon has edges")
+    };
+
+    let path = Segment::from((start, end));
+    let entered = edges
+        .iter()
+        .filter_map(|edge| Segment::intersection(&path, edge).map(|x| (edge, x)))
+        .min_by(|(_, a), (_, b)| {
+            Vec2::dist_scalar(*a, start).total_cmp(&Vec2::dist_scalar(*b, start))
+        });
+
+    let (edge, normal) = if let (false, Some((edge, _))) = (inside(start), entered) {
+        // crossed into the polygon within the step: push it back out of the side it went in through
+        (edge, outward(edge))
+    } else if inside(end) {
+        // already inside: push it out of the nearest side
+        let edge = nearest(end);
+        (edge, outward(edge))
+    } else {
+        let edge = nearest(end);
+        let offset = end - edge.closest_point(end);
+        if offset.len() >= radius {
+            return None;
+        }
+        (edge, offset.normalize())
+    };
+
+    Some(SurfaceContact {
+        position: edge.closest_point(end) + normal * radius,
+        normal,
+    })
+}
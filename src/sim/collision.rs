@@ -0,0 +1,175 @@
+use super::arena::ObjectId;
+use super::object::Object;
+use super::vec2::Vec2;
+
+/// A contact between two objects found while sweeping them over a step.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Contact {
+    /// The first object.
+    pub a: ObjectId,
+
+    /// The second object.
+    pub b: ObjectId,
+
+    /// Fraction of the step (between `0.0` and `1.0`) at which the objects first touch.
+    pub time_of_impact: f64,
+
+    /// Unit vector pointing from `a` towards `b` at the time of impact.
+    pub normal: Vec2,
+
+    /// How deep the objects would overlap at the end of the step if the contact was left unresolved.
+    pub depth: f64,
+
+    /// How far `b` was moved to bring it next to `a` across a wrapping boundary. Zero for ordinary contacts.
+    pub offset: Vec2,
+}
+
+impl Contact {
+    /// Sweeps two circles from `position` to `next_position` and returns the first time they touch within the
+    /// step, if any.
+    ///
+    /// Both circles are assumed to move in a straight line at constant speed over the step, which reduces the
+    /// problem to finding the smallest `t` in `0.0..=1.0` satisfying `|p + d * t| = r_a + r_b`, where `p` is the
+    /// relative position at the start of the step and `d` is the relative displacement over it.
+    pub fn between(
+        a: ObjectId,
+        object_a: &Object,
+        radius_a: f64,
+        b: ObjectId,
+        object_b: &Object,
+        radius_b: f64,
+    ) -> Option<Contact> {
+        let radii = radius_a + radius_b;
+
+        let start = object_b.position - object_a.position;
+        let end = object_b.next_position - object_a.next_position;
+        let displacement = end - start;
+
+        let depth = (radii - end.len()).max(0.0);
+
+        // already overlapping at the start of the step
+        if start.len() < radii {
+            return Some(Contact {
+                a,
+                b,
+                time_of_impact: 0.0,
+                normal: Self::normal_or_default(start, displacement),
+                depth,
+                offset: Vec2 { x: 0.0, y: 0.0 },
+            });
+        }
+
+        // solve the quadratic a*t^2 + b*t + c = 0
+        let qa = displacement.dot(displacement);
+        let qb = 2.0 * start.dot(displacement);
+        let qc = start.dot(start) - radii * radii;
+
+        if qa == 0.0 {
+            // no relative movement, and not overlapping
+            return None;
+        }
+
+        let discriminant = qb * qb - 4.0 * qa * qc;
+        if discriminant < 0.0 {
+            // the circles never get close enough to touch
+            return None;
+        }
+
+        let time_of_impact = (-qb - discriminant.sqrt()) / (2.0 * qa);
+        if !(0.0..=1.0).contains(&time_of_impact) {
+            return None;
+        }
+
+        Some(Contact {
+            a,
+            b,
+            time_of_impact,
+            normal: Self::normal_or_default(start + displacement * time_of_impact, displacement),
+            depth,
+            offset: Vec2 { x: 0.0, y: 0.0 },
+        })
+    }
+
+    /// Normalizes `offset`, falling back to the direction opposite `displacement` (or straight down) when the
+    /// centres coincide.
+    fn normal_or_default(offset: Vec2, displacement: Vec2) -> Vec2 {
+        if offset.len() != 0.0 {
+            offset.normalize()
+        } else if displacement.len() != 0.0 {
+            (-displacement).normalize()
+        } else {
+            Vec2 { x: 0.0, y: 1.0 }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::arena::Arena;
+    use crate::vec2;
+
+    /// Sweeps two balls of radius 10, `a` moving from `a.0` to `a.1` and `b` from `b.0` to `b.1` over the step.
+    fn sweep(a: (Vec2, Vec2), b: (Vec2, Vec2)) -> Option<Contact> {
+        let mut ids = Arena::new();
+        let ball = |(position, next_position): (Vec2, Vec2)| Object {
+            next_position,
+            ..Object::new(position, vec2!(0, 0), 10.0)
+        };
+
+        let (object_a, object_b) = (ball(a), ball(b));
+        Contact::between(
+            ids.insert(()),
+            &object_a,
+            10.0,
+            ids.insert(()),
+            &object_b,
+            10.0,
+        )
+    }
+
+    #[test]
+    fn balls_passing_through_each_other_within_a_step_are_caught_where_they_first_touch() {
+        let contact = sweep((vec2!(0, 0), vec2!(100, 0)), (vec2!(100, 0), vec2!(0, 0))).unwrap();
+
+        // the centres close 200 pixels over the step, and touch 20 pixels apart
+        assert!((contact.time_of_impact - 0.4).abs() < 1e-12);
+        assert_eq!(contact.normal, vec2!(1, 0));
+        // they end the step apart again, on the wrong sides of each other
+        assert_eq!(contact.depth, 0.0);
+    }
+
+    #[test]
+    fn balls_grazing_past_each_other_only_touch_if_they_come_close_enough() {
+        let miss = sweep(
+            (vec2!(0, 0), vec2!(0, 0)),
+            (vec2!(-50, 20.5), vec2!(50, 20.5)),
+        );
+        assert_eq!(miss, None);
+
+        let graze = sweep(
+            (vec2!(0, 0), vec2!(0, 0)),
+            (vec2!(-50, 19.5), vec2!(50, 19.5)),
+        )
+        .unwrap();
+        assert!(graze.time_of_impact > 0.0 && graze.time_of_impact < 0.5);
+        assert!(graze.normal.y > 0.9);
+    }
+
+    #[test]
+    fn balls_overlapping_at_the_start_touch_straight_away() {
+        let contact = sweep((vec2!(0, 0), vec2!(0, 0)), (vec2!(15, 0), vec2!(15, 0))).unwrap();
+
+        assert_eq!(contact.time_of_impact, 0.0);
+        assert_eq!(contact.normal, vec2!(1, 0));
+        assert!((contact.depth - 5.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn depth_is_how_far_the_balls_would_overlap_at_the_end_of_the_step() {
+        let contact = sweep((vec2!(0, 0), vec2!(0, 0)), (vec2!(50, 0), vec2!(12, 0))).unwrap();
+
+        assert!((contact.time_of_impact - 30.0 / 38.0).abs() < 1e-12);
+        assert!((contact.depth - 8.0).abs() < 1e-12);
+    }
+}
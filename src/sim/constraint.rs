@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use super::arena::ObjectId;
+use super::object::Object;
+use super::vec2::Vec2;
+
+/// How a constraint holds its two objects together.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Link {
+    /// A rigid rod: the centres are kept exactly `length` apart.
+    Distance { length: f64 },
+
+    /// A Hookean spring, pulling the centres towards `rest_length` apart with a force of `stiffness` per pixel of
+    /// stretch, and resisting the speed at which it stretches with `damping` per pixel per second.
+    Spring {
+        rest_length: f64,
+        stiffness: f64,
+        damping: f64,
+    },
+
+    /// A rope: the centres can't get more than `length` apart, but can come as close as they like.
+    Rope { length: f64 },
+}
+
+impl Link {
+    /// Whether every length, stiffness and damping of the link is a finite number, and not negative.
+    pub fn is_valid(&self) -> bool {
+        let valid = |value: f64| value >= 0.0 && value.is_finite();
+        match *self {
+            Link::Distance { length } | Link::Rope { length } => valid(length),
+            Link::Spring {
+                rest_length,
+                stiffness,
+                damping,
+            } => valid(rest_length) && valid(stiffness) && valid(damping),
+        }
+    }
+}
+
+/// A link between two objects in a `Solver`.
+///
+/// Springs are forces, applied before integrating. Distance and rope links are solved on positions after the
+/// collisions, and the objects' velocities then follow the corrections.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Constraint {
+    pub a: ObjectId,
+    pub b: ObjectId,
+    pub link: Link,
+}
+
+impl Constraint {
+    pub fn new(a: ObjectId, b: ObjectId, link: Link) -> Constraint {
+        Constraint { a, b, link }
+    }
+
+    /// Applies the spring force between the two objects over `dt` seconds, if the link is a spring.
+    ///
+    /// `w1` and `w2` are the objects' inverse masses.
+    pub fn apply_force(
+        &self,
+        object1: &mut Object,
+        object2: &mut Object,
+        (w1, w2): (f64, f64),
+        dt: f64,
+    ) {
+        let Link::Spring {
+            rest_length,
+            stiffness,
+            damping,
+        } = self.link
+        else {
+            return;
+        };
+
+        let Some((normal, distance)) = axis(object1.next_position, object2.next_position) else {
+            return;
+        };
+
+        // Hooke's law plus damping on the speed the spring stretches at, pulling the objects together when positive
+        let stretch_speed = (object2.next_velocity - object1.next_velocity).dot(normal);
+        let force = stiffness * (distance - rest_length) + damping * stretch_speed;
+
+        object1.next_velocity += normal * (force * w1 * dt);
+        object2.next_velocity -= normal * (force * w2 * dt);
+    }
+
+    /// Moves the two objects' next positions so the link holds, in inverse proportion to their masses. Springs are
+    /// left alone.
+    ///
+    /// `w1` and `w2` are the objects' inverse masses; at least one of them should be positive.
+    pub fn project(&self, object1: &mut Object, object2: &mut Object, (w1, w2): (f64, f64)) {
+        let (length, rope) = match self.link {
+            Link::Distance { length } => (length, false),
+            Link::Rope { length } => (length, true),
+            Link::Spring { .. } => return,
+        };
+
+        let Some((normal, distance)) = axis(object1.next_position, object2.next_position) else {
+            return;
+        };
+
+        // a rope only pulls, it never pushes
+        let error = distance - length;
+        if rope && error <= 0.0 {
+            return;
+        }
+
+        let w = w1 + w2;
+        object1.next_position += normal * (error * w1 / w);
+        object2.next_position -= normal * (error * w2 / w);
+    }
+}
+
+/// Unit vector from `from` to `to`, and the distance between them. `None` if they are in the same place.
+fn axis(from: Vec2, to: Vec2) -> Option<(Vec2, f64)> {
+    let distance = Vec2::dist_scalar(from, to);
+    (distance != 0.0).then(|| ((to - from) * (1.0 / distance), distance))
+}
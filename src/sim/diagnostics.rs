@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+
+use super::vec2::Vec2;
+
+/// Denominator floor for relative drift, so quantities that start at zero don't divide by zero.
+const DRIFT_FLOOR: f64 = 1e-9;
+
+/// Conserved quantities of a solver at one point in time, from `Solver::diagnostics`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Diagnostics {
+    /// Simulated time the snapshot was taken at, in seconds.
+    pub time: f64,
+
+    pub kinetic_energy: f64,
+
+    /// Gravitational potential energy, measured as described in `ForceModel::potential_energies`.
+    pub potential_energy: f64,
+
+    /// Kinetic plus potential energy.
+    pub total_energy: f64,
+
+    /// Total linear momentum.
+    pub momentum: Vec2,
+
+    /// Total angular momentum about the centre of the solver's bounds.
+    pub angular_momentum: f64,
+}
+
+/// How far a snapshot has moved from a reference snapshot, relative to the reference.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Drift {
+    pub energy: f64,
+
+    /// Length of the change in momentum, so always positive.
+    pub momentum: f64,
+
+    pub angular_momentum: f64,
+}
+
+impl Diagnostics {
+    /// Drift of this snapshot from `reference`.
+    ///
+    /// Energy and angular momentum are signed. Each change is divided by the size of the reference value; when that
+    /// is zero (say, everything starts at rest) the change is effectively absolute.
+    pub fn drift(&self, reference: &Diagnostics) -> Drift {
+        let relative = |change: f64, scale: f64| change / scale.abs().max(DRIFT_FLOOR);
+
+        Drift {
+            energy: relative(
+                self.total_energy - reference.total_energy,
+                reference.total_energy,
+            ),
+            momentum: relative(
+                (self.momentum - reference.momentum).len(),
+                reference.momentum.len(),
+            ),
+            angular_momentum: relative(
+                self.angular_momentum - reference.angular_momentum,
+                reference.angular_momentum,
+            ),
+        }
+    }
+}
+
+/// The last few snapshots of a run, and the first one, to follow drift over time.
+#[derive(Debug, Clone)]
+pub struct History {
+    reference: Option<Diagnostics>,
+    samples: VecDeque<Diagnostics>,
+    capacity: usize,
+}
+
+impl History {
+    /// An empty history keeping at most `capacity` snapshots.
+    pub fn new(capacity: usize) -> History {
+        History {
+            reference: None,
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Adds a snapshot, dropping the oldest one if full. The first snapshot after `clear` becomes the reference.
+    pub fn push(&mut self, diagnostics: Diagnostics) {
+        self.reference.get_or_insert(diagnostics);
+
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(diagnostics);
+    }
+
+    /// Forgets everything, e.g. after objects are added or removed and the old reference no longer means anything.
+    pub fn clear(&mut self) {
+        self.reference = None;
+        self.samples.clear();
+    }
+
+    /// The snapshot drift is measured from.
+    pub fn reference(&self) -> Option<&Diagnostics> {
+        self.reference.as_ref()
+    }
+
+    /// Drift of every kept snapshot from the reference, oldest first.
+    pub fn drifts(&self) -> Vec<Drift> {
+        match &self.reference {
+            Some(reference) => self.samples.iter().map(|i| i.drift(reference)).collect(),
+            None => vec![],
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Writing the state of every object at every step, for analysis elsewhere.
+//!
+//! Objects are identified by `ObjectId::to_bits`, which stays the same for an object's whole life even when others
+//! are removed.
+//!
+//! Two formats are supported:
+//!
+//! - CSV, with a header row: `step,time,object,x,y,vx,vy,energy`.
+//! - A compact little-endian binary columnar format, laid out as
+//!   - the magic bytes `GRVC`,
+//!   - the format version as a `u32` (currently `1`),
+//!   - the number of rows as a `u64`,
+//!   - the number of columns as a `u32`,
+//!   - then for each column: its name as a `u16` byte length followed by UTF-8, a type tag `u8` (`0` for `u64`,
+//!     `1` for `f64`), and every row's value.
+//!
+//!   The columns are the same as the CSV's. Reading a column means skipping over the ones before it, so e.g. all
+//!   `x` values can be loaded with a single read, as with Parquet or Arrow.
+
+use std::io::{self, Write};
+
+use super::solver::Solver;
+
+/// Magic bytes at the start of a columnar file.
+pub const COLUMNAR_MAGIC: &[u8; 4] = b"GRVC";
+
+/// Version of the columnar format written by this build.
+pub const COLUMNAR_VERSION: u32 = 1;
+
+/// Names of the exported columns, in order.
+pub const COLUMNS: [&str; 8] = ["step", "time", "object", "x", "y", "vx", "vy", "energy"];
+
+/// Writes the state of a solver at a series of steps.
+pub trait Exporter {
+    /// Writes the state of every object in `solver` as of `step`.
+    fn write_step(&mut self, step: u64, solver: &Solver) -> io::Result<()>;
+
+    /// Flushes anything buffered. Nothing can be written afterwards.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Exports to CSV, one row per object per step.
+pub struct CsvExporter<W: Write> {
+    output: W,
+    header_written: bool,
+}
+
+impl<W: Write> CsvExporter<W> {
+    pub fn new(output: W) -> CsvExporter<W> {
+        CsvExporter {
+            output,
+            header_written: false,
+        }
+    }
+}
+
+impl<W: Write> Exporter for CsvExporter<W> {
+    fn write_step(&mut self, step: u64, solver: &Solver) -> io::Result<()> {
+        if !self.header_written {
+            writeln!(self.output, "{}", COLUMNS.join(","))?;
+            self.header_written = true;
+        }
+
+        for ((id, i), energy) in solver.iter().zip(solver.energies()) {
+            writeln!(
+                self.output,
+                "{},{},{},{},{},{},{},{}",
+                step,
+                solver.time,
+                id.to_bits(),
+                i.position.x,
+                i.position.y,
+                i.velocity.x,
+                i.velocity.y,
+                energy
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// A column of the binary columnar format.
+enum Column {
+    U64(Vec<u64>),
+    F64(Vec<f64>),
+}
+
+/// Exports to the binary columnar format described in the module documentation.
+///
+/// Columns are kept in memory until `finish`, since every value of a column is written together. Use
+/// `with_max_rows` to bound how much is kept when the number of steps isn't known up front.
+pub struct ColumnarExporter<W: Write> {
+    output: W,
+    rows: u64,
+    /// Most rows kept before `write_step` fails, if limited.
+    max_rows: Option<u64>,
+    /// One per entry of `COLUMNS`.
+    columns: Vec<Column>,
+}
+
+impl<W: Write> ColumnarExporter<W> {
+    pub fn new(output: W) -> ColumnarExporter<W> {
+        let columns = COLUMNS
+            .iter()
+            .map(|&name| match name {
+                "step" | "object" => Column::U64(vec![]),
+                _ => Column::F64(vec![]),
+            })
+            .collect();
+
+        ColumnarExporter {
+            output,
+            rows: 0,
+            max_rows: None,
+            columns,
+        }
+    }
+
+    /// Limits the export to `max_rows` rows. A step that doesn't fit makes `write_step` fail without writing any of
+    /// it, and the rows so far can still be written out with `finish`.
+    pub fn with_max_rows(mut self, max_rows: u64) -> ColumnarExporter<W> {
+        self.max_rows = Some(max_rows);
+        self
+    }
+}
+
+impl<W: Write> Exporter for ColumnarExporter<W> {
+    fn write_step(&mut self, step: u64, solver: &Solver) -> io::Result<()> {
+        if let Some(max_rows) = self.max_rows {
+            if self.rows + solver.objects.len() as u64 > max_rows {
+                return Err(io::Error::other(format!(
+                    "columnar export is limited to {} rows",
+                    max_rows
+                )));
+            }
+        }
+
+        for ((id, i), energy) in solver.iter().zip(solver.energies()) {
+            let integers = [step, id.to_bits()];
+            let floats = [
+                solver.time,
+                i.position.x,
+                i.position.y,
+                i.velocity.x,
+                i.velocity.y,
+                energy,
+            ];
+            let (mut integers, mut floats) = (integers.into_iter(), floats.into_iter());
+
+            // values are taken in column order from whichever list matches the column's type
+            for column in &mut self.columns {
+                match column {
+                    Column::U64(values) => values.extend(integers.next()),
+                    Column::F64(values) => values.extend(floats.next()),
+                }
+            }
+            self.rows += 1;
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.output.write_all(COLUMNAR_MAGIC)?;
+        self.output.write_all(&COLUMNAR_VERSION.to_le_bytes())?;
+        self.output.write_all(&self.rows.to_le_bytes())?;
+        self.output
+            .write_all(&(COLUMNS.len() as u32).to_le_bytes())?;
+
+        for (name, column) in COLUMNS.iter().zip(std::mem::take(&mut self.columns)) {
+            self.output.write_all(&(name.len() as u16).to_le_bytes())?;
+            self.output.write_all(name.as_bytes())?;
+
+            match column {
+                Column::U64(values) => {
+                    self.output.write_all(&[0])?;
+                    for value in values {
+                        self.output.write_all(&value.to_le_bytes())?;
+                    }
+                }
+                Column::F64(values) => {
+                    self.output.write_all(&[1])?;
+                    for value in values {
+                        self.output.write_all(&value.to_le_bytes())?;
+                    }
+                }
+            }
+        }
+
+        self.output.flush()
+    }
+}
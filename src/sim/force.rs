@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use super::object::Object;
+use super::quadtree::barnes_hut_accelerations;
+use super::vec2::Vec2;
+
+/// Gravitational constant used by `ForceModel::mutual`.
+/// Measured in pixels cubed per mass unit per second squared
+pub const GRAVITATIONAL_CONSTANT: f64 = 100_000.0;
+
+/// Softening length used by `ForceModel::mutual`.
+/// Measured in pixels
+pub const SOFTENING: f64 = 10.0;
+
+/// Opening angle used by `ForceModel::barnes_hut`.
+pub const OPENING_ANGLE: f64 = 0.5;
+
+/// How objects are accelerated by gravity.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ForceModel {
+    /// Every object falls in the same constant, downward gravitational field.
+    #[default]
+    #[serde(rename = "uniform")]
+    UniformField,
+
+    /// Every object attracts every other object following Newton's law of universal gravitation.
+    ///
+    /// `softening` is added in quadrature to the distance between two objects, so that close encounters don't
+    /// produce infinite accelerations: `a = g * m * r / (|r|² + softening²)^(3/2)`.
+    Mutual { g: f64, softening: f64 },
+
+    /// Same as `Mutual`, but approximated with a Barnes–Hut quadtree in O(n log n).
+    ///
+    /// `theta` is the opening angle: groups of objects that appear smaller than `theta` (their size divided by
+    /// their distance) are treated as a single point mass.
+    BarnesHut { g: f64, softening: f64, theta: f64 },
+}
+
+impl ForceModel {
+    /// Mutual gravitation with the default constant and softening length.
+    pub fn mutual() -> ForceModel {
+        ForceModel::Mutual {
+            g: GRAVITATIONAL_CONSTANT,
+            softening: SOFTENING,
+        }
+    }
+
+    /// Barnes–Hut gravitation with the default constant, softening length and opening angle.
+    pub fn barnes_hut() -> ForceModel {
+        ForceModel::BarnesHut {
+            g: GRAVITATIONAL_CONSTANT,
+            softening: SOFTENING,
+            theta: OPENING_ANGLE,
+        }
+    }
+
+    /// Looks up a force model with its default constants by name: `uniform`, `mutual` or `barnes-hut`.
+    pub fn by_name(name: &str) -> Option<ForceModel> {
+        match name {
+            "uniform" => Some(ForceModel::UniformField),
+            "mutual" => Some(ForceModel::mutual()),
+            "barnes-hut" => Some(ForceModel::barnes_hut()),
+            _ => None,
+        }
+    }
+
+    /// Whether every constant of the force model is a finite number.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            ForceModel::UniformField => true,
+            ForceModel::Mutual { g, softening } => g.is_finite() && softening.is_finite(),
+            ForceModel::BarnesHut {
+                g,
+                softening,
+                theta,
+            } => g.is_finite() && softening.is_finite() && theta.is_finite(),
+        }
+    }
+
+    /// Acceleration of every object, evaluated at `next_position`.
+    ///
+    /// `gravity` is the field strength used by `UniformField`.
+    pub fn accelerations(&self, objects: &[Object], gravity: Vec2) -> Vec<Vec2> {
+        match *self {
+            ForceModel::UniformField => vec![gravity; objects.len()],
+            ForceModel::Mutual { g, softening } => mutual_accelerations(objects, g, softening),
+            ForceModel::BarnesHut {
+                g,
+                softening,
+                theta,
+            } => barnes_hut_accelerations(objects, g, softening, theta),
+        }
+    }
+
+    /// Gravitational potential energy of every object, evaluated at `position`.
+    ///
+    /// For `UniformField` this is measured from `y = 0` along `gravity`. For mutual gravitation each pair's
+    /// potential energy is split evenly between the two objects, so the values add up to the total. Barnes–Hut uses
+    /// the exact pairwise sum.
+    pub fn potential_energies(&self, objects: &[Object], gravity: Vec2) -> Vec<f64> {
+        match *self {
+            ForceModel::UniformField => objects
+                .iter()
+                .map(|i| -i.mass * gravity.dot(i.position))
+                .collect(),
+            ForceModel::Mutual { g, softening } | ForceModel::BarnesHut { g, softening, .. } => {
+                mutual_potential_energies(objects, g, softening)
+            }
+        }
+    }
+}
+
+/// Potential energy of every object due to every other object, with each pair's share split evenly. O(n²).
+pub fn mutual_potential_energies(objects: &[Object], g: f64, softening: f64) -> Vec<f64> {
+    let mut energies = vec![0.0; objects.len()];
+
+    for (idx, i) in objects.iter().enumerate() {
+        for (jdx, j) in objects.iter().enumerate().skip(idx + 1) {
+            let r = j.position - i.position;
+            let energy = -g * i.mass * j.mass / (r.dot(r) + softening * softening).sqrt();
+
+            energies[idx] += energy / 2.0;
+            energies[jdx] += energy / 2.0;
+        }
+    }
+
+    energies
+}
+
+/// Acceleration of every object due to every other object, summed pairwise. O(n²).
+pub fn mutual_accelerations(objects: &[Object], g: f64, softening: f64) -> Vec<Vec2> {
+    let mut accelerations = vec![Vec2 { x: 0.0, y: 0.0 }; objects.len()];
+
+    for (idx, i) in objects.iter().enumerate() {
+        for (jdx, j) in objects.iter().enumerate().skip(idx + 1) {
+            let r = j.next_position - i.next_position;
+            let inv_cube = (r.dot(r) + softening * softening).powf(-1.5);
+
+            // equal and opposite: each pair is only visited once
+            accelerations[idx] += r * (g * j.mass * inv_cube);
+            accelerations[jdx] -= r * (g * i.mass * inv_cube);
+        }
+    }
+
+    accelerations
+}
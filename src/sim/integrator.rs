@@ -0,0 +1,225 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use super::object::Object;
+use super::vec2::Vec2;
+
+/// Computes the acceleration of every object from their `next_position`.
+pub type Accelerations<'a> = dyn Fn(&[Object]) -> Vec<Vec2> + 'a;
+
+/// Numerical scheme advancing objects through time.
+///
+/// Integrators work on `next_position` and `next_velocity`, so that wall and object collisions can be resolved
+/// around them before the new state is committed.
+pub trait Integrator: Debug + Send + Sync {
+    /// Short human readable name.
+    fn name(&self) -> &'static str;
+
+    /// Advances every object by `dt` seconds.
+    fn integrate(&self, objects: &mut [Object], dt: f64, accelerations: &Accelerations);
+}
+
+/// Semi-implicit (symplectic) Euler: kick, then drift with the new velocity. First order, one force evaluation.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SemiImplicitEuler;
+
+/// Velocity Verlet (kick-drift-kick leapfrog). Second order and symplectic, two force evaluations.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct VelocityVerlet;
+
+/// Drift-kick-drift leapfrog. Second order and symplectic, one force evaluation.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Leapfrog;
+
+/// Classic fourth order Runge–Kutta. Very accurate over short spans, but not symplectic, so energy slowly drifts
+/// over long runs. Four force evaluations.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RungeKutta4;
+
+/// Looks up one of the built-in integrators by its `name`.
+pub fn by_name(name: &str) -> Option<Arc<dyn Integrator>> {
+    let integrators: [Arc<dyn Integrator>; 4] = [
+        Arc::new(SemiImplicitEuler),
+        Arc::new(VelocityVerlet),
+        Arc::new(Leapfrog),
+        Arc::new(RungeKutta4),
+    ];
+
+    integrators.into_iter().find(|i| i.name() == name)
+}
+
+/// Copies `objects` with `next_position` moved to `positions`.
+fn at_positions(objects: &[Object], positions: &[Vec2]) -> Vec<Object> {
+    objects
+        .iter()
+        .zip(positions)
+        .map(|(i, &position)| Object {
+            next_position: position,
+            ..i.clone()
+        })
+        .collect()
+}
+
+impl Integrator for SemiImplicitEuler {
+    fn name(&self) -> &'static str {
+        "semi-implicit euler"
+    }
+
+    fn integrate(&self, objects: &mut [Object], dt: f64, accelerations: &Accelerations) {
+        let a = accelerations(objects);
+
+        for (i, a) in objects.iter_mut().zip(a) {
+            // next_velocity = current_velocity + dt*current_acceleration
+            i.next_velocity += a * dt;
+
+            // next_position = current_position + dt*next_velocity
+            i.next_position += i.next_velocity * dt;
+        }
+    }
+}
+
+impl Integrator for VelocityVerlet {
+    fn name(&self) -> &'static str {
+        "velocity verlet"
+    }
+
+    fn integrate(&self, objects: &mut [Object], dt: f64, accelerations: &Accelerations) {
+        let a0 = accelerations(objects);
+
+        for (i, &a) in objects.iter_mut().zip(&a0) {
+            i.next_position += i.next_velocity * dt + a * (0.5 * dt * dt);
+        }
+
+        let a1 = accelerations(objects);
+
+        for ((i, a0), a1) in objects.iter_mut().zip(a0).zip(a1) {
+            i.next_velocity += (a0 + a1) * (0.5 * dt);
+        }
+    }
+}
+
+impl Integrator for Leapfrog {
+    fn name(&self) -> &'static str {
+        "leapfrog"
+    }
+
+    fn integrate(&self, objects: &mut [Object], dt: f64, accelerations: &Accelerations) {
+        // drift half a step
+        for i in objects.iter_mut() {
+            i.next_position += i.next_velocity * (0.5 * dt);
+        }
+
+        // acceleration at the midpoint of the step
+        let a = accelerations(objects);
+
+        // kick a whole step with it, then drift the other half with the new velocity
+        for (i, a) in objects.iter_mut().zip(a) {
+            i.next_velocity += a * dt;
+            i.next_position += i.next_velocity * (0.5 * dt);
+        }
+    }
+}
+
+impl Integrator for RungeKutta4 {
+    fn name(&self) -> &'static str {
+        "rk4"
+    }
+
+    fn integrate(&self, objects: &mut [Object], dt: f64, accelerations: &Accelerations) {
+        let x: Vec<Vec2> = objects.iter().map(|i| i.next_position).collect();
+        let v: Vec<Vec2> = objects.iter().map(|i| i.next_velocity).collect();
+
+        // state offset by `k * h`, for both position and velocity
+        let offset = |base: &[Vec2], k: &[Vec2], h: f64| -> Vec<Vec2> {
+            base.iter().zip(k).map(|(&b, &k)| b + k * h).collect()
+        };
+
+        let k1x = v.clone();
+        let k1v = accelerations(objects);
+
+        let k2x = offset(&v, &k1v, 0.5 * dt);
+        let k2v = accelerations(&at_positions(objects, &offset(&x, &k1x, 0.5 * dt)));
+
+        let k3x = offset(&v, &k2v, 0.5 * dt);
+        let k3v = accelerations(&at_positions(objects, &offset(&x, &k2x, 0.5 * dt)));
+
+        let k4x = offset(&v, &k3v, dt);
+        let k4v = accelerations(&at_positions(objects, &offset(&x, &k3x, dt)));
+
+        for (n, i) in objects.iter_mut().enumerate() {
+            i.next_position += (k1x[n] + k2x[n] * 2.0 + k3x[n] * 2.0 + k4x[n]) * (dt / 6.0);
+            i.next_velocity += (k1v[n] + k2v[n] * 2.0 + k3v[n] * 2.0 + k4v[n]) * (dt / 6.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Strength of the point mass at the origin the test orbits go around, `G * M`.
+    const GM: f64 = 1_000_000.0;
+
+    /// Largest drift in specific orbital energy, relative to the starting energy, over ten circular orbits of radius
+    /// 100 taken in steps of `dt`.
+    fn orbit_energy_drift(integrator: &dyn Integrator, dt: f64) -> f64 {
+        let radius = 100.0;
+        let speed = (GM / radius).sqrt();
+        let mut objects = vec![Object::new(
+            Vec2 { x: radius, y: 0.0 },
+            Vec2 { x: 0.0, y: speed },
+            1.0,
+        )];
+
+        let accelerations = |objects: &[Object]| -> Vec<Vec2> {
+            objects
+                .iter()
+                .map(|i| {
+                    let r = i.next_position;
+                    r * (-GM / r.len().powi(3))
+                })
+                .collect()
+        };
+        let energy =
+            |i: &Object| 0.5 * i.next_velocity.dot(i.next_velocity) - GM / i.next_position.len();
+
+        let start = energy(&objects[0]);
+        let period = std::f64::consts::TAU * radius / speed;
+        let steps = (10.0 * period / dt).round() as usize;
+
+        let mut drift: f64 = 0.0;
+        for _ in 0..steps {
+            integrator.integrate(&mut objects, dt, &accelerations);
+            drift = drift.max(((energy(&objects[0]) - start) / start).abs());
+        }
+        drift
+    }
+
+    #[test]
+    fn every_integrator_keeps_a_circular_orbit() {
+        for (integrator, bound) in [
+            (by_name("semi-implicit euler").unwrap(), 1e-3),
+            (by_name("velocity verlet").unwrap(), 1e-7),
+            (by_name("leapfrog").unwrap(), 1e-7),
+            (by_name("rk4").unwrap(), 1e-8),
+        ] {
+            let drift = orbit_energy_drift(&*integrator, 0.01);
+            assert!(drift < bound, "{} drifted by {}", integrator.name(), drift);
+        }
+    }
+
+    #[test]
+    fn higher_order_integrators_beat_semi_implicit_euler() {
+        let euler = orbit_energy_drift(&SemiImplicitEuler, 0.01);
+        for integrator in [&VelocityVerlet as &dyn Integrator, &Leapfrog, &RungeKutta4] {
+            let drift = orbit_energy_drift(integrator, 0.01);
+            assert!(
+                drift < euler,
+                "{} drifted by {}, euler by {}",
+                integrator.name(),
+                drift,
+                euler
+            );
+        }
+    }
+}
@@ -1,7 +1,22 @@
-// export sim crate
+//! Physics simulation: objects, forces, integrators and collisions.
 
+pub mod arena;
+pub mod boundary;
+pub mod broadphase;
+pub mod collider;
+pub mod collision;
+pub mod constraint;
+pub mod diagnostics;
+pub mod export;
+pub mod force;
+pub mod integrator;
 pub mod object;
+pub mod params;
+pub mod preset;
+pub mod quadtree;
+pub mod replay;
+pub mod scene;
 pub mod segment;
+pub mod softbody;
 pub mod solver;
-pub mod util;
 pub mod vec2;
@@ -1,34 +1,63 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
+use serde::{Deserialize, Serialize};
 
 use super::vec2::Vec2;
-use crate::vec2;
 
-/// Gravity constant.
-/// Measured in pixels per second squared
-const GRAVITY: Vec2 = vec2!(0, 800); // in pixels per second squared
+/// Default radius of an object.
+/// Measured in pixels
+pub const OBJECT_RADIUS: f64 = 10.0;
 
-/// Bounce Constant (aka deflective efficacy).
-///
-/// Represents how much energy is deflected off the floor back into the ball.
-///
-/// The following equation is always satisfied (in real life terms):
-/// ```
-/// 0.0 < BOUNCE_CONSTANT < 1.0
-/// ```
-const BOUNCE_CONSTANT: f64 = 0.60;
+/// Default bounce constant of an object (coefficient of restitution).
+pub const OBJECT_RESTITUTION: f64 = 0.90;
+
+/// Default friction constant of an object (Coulomb coefficient of friction).
+pub const OBJECT_FRICTION: f64 = 0.3;
 
-/// Friction constant.
+/// Default color of an object (opaque white).
+pub const OBJECT_COLOR: [f32; 4] = [1.0; 4];
+
+/// Combines the material constants (restitution or friction) of two surfaces in contact.
 ///
-/// Represents how much friction the ball experiences when touching the ground.
-const FRICTION: f64 = 0.01;
+/// Uses the geometric mean, so two identical surfaces keep their own constant and a surface with a constant of
+/// `0.0` always wins.
+pub fn combine(a: f64, b: f64) -> f64 {
+    (a * b).sqrt()
+}
+
+/// How an object takes part in the simulation.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BodyType {
+    /// Moved by forces, collisions, constraints and the walls.
+    #[default]
+    Dynamic,
+
+    /// Moves in a straight line at its own velocity and spins at its own angular velocity, unless they are changed
+    /// from outside, e.g. by the mouse. Nothing in the simulation pushes it back: it acts as if it were infinitely
+    /// heavy, shoving dynamic objects out of its way, and passes through the walls and static colliders.
+    Kinematic,
+
+    /// Never moves, e.g. a pinned point. Acts as if it were infinitely heavy, so objects still bounce off it and hang
+    /// from it.
+    Static,
+}
+
+impl BodyType {
+    /// Name of the body type, as written in scene files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BodyType::Dynamic => "dynamic",
+            BodyType::Kinematic => "kinematic",
+            BodyType::Static => "static",
+        }
+    }
+}
 
 /// Gravity-affected object
 ///
 /// An object with a position and velocity.
-/// Uses semi-implicit Euler's method to compute next postition and velocity from old position, velocity, and
+/// Each step, `Solver::integrator` computes its next position and velocity from its old position, velocity, and
 /// acceleration.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Object {
     /// The position of the Object.
     pub position: Vec2,
@@ -40,5 +69,183 @@ pub struct Object {
 
     pub next_position: Vec2,
 
+    /// The position of the Object before the last step, used to interpolate between steps when drawing.
+    pub previous_position: Vec2,
+
+    /// How far the Object has turned, clockwise on screen.
+    /// Measured in radians
+    pub angle: f64,
+
+    /// How fast the Object spins, clockwise on screen.
+    /// Measured in radians per second
+    pub angular_velocity: f64,
+
+    pub next_angle: f64,
+
+    pub next_angular_velocity: f64,
+
+    /// The angle of the Object before the last step, used to interpolate between steps when drawing.
+    pub previous_angle: f64,
+
     pub mass: f64,
+
+    /// The radius of the Object.
+    pub radius: f64,
+
+    /// Bounce constant of the Object's surface, combined with the other surface's in a collision.
+    ///
+    /// `1.0` is perfectly elastic, `0.0` is perfectly inelastic.
+    pub restitution: f64,
+
+    /// Friction constant of the Object's surface, combined with the other surface's in a collision.
+    ///
+    /// Coulomb coefficient of friction: the sliding impulse at a contact is at most this much of the normal impulse.
+    /// Friction turns sliding into spin, so a ball thrown along the floor slides and then rolls.
+    pub friction: f64,
+
+    /// The color the Object is drawn in, as RGBA.
+    pub color: [f32; 4],
+
+    /// Whether the Object has come to rest and is no longer integrated. Sleeping objects don't budge when touched,
+    /// unless hit hard enough to wake them.
+    pub asleep: bool,
+
+    /// How long the Object has been resting, towards falling asleep.
+    /// Measured in seconds
+    pub rest_time: f64,
+
+    /// Whether the Object is moved by the simulation, by its own velocity, or not at all.
+    pub body_type: BodyType,
+}
+
+impl Object {
+    pub fn new(position: Vec2, velocity: Vec2, mass: f64) -> Object {
+        Object {
+            position,
+            velocity,
+            next_velocity: velocity,
+            next_position: position,
+            previous_position: position,
+            angle: 0.0,
+            angular_velocity: 0.0,
+            next_angle: 0.0,
+            next_angular_velocity: 0.0,
+            previous_angle: 0.0,
+            mass,
+            radius: OBJECT_RADIUS,
+            restitution: OBJECT_RESTITUTION,
+            friction: OBJECT_FRICTION,
+            color: OBJECT_COLOR,
+            asleep: false,
+            rest_time: 0.0,
+            body_type: BodyType::Dynamic,
+        }
+    }
+
+    pub fn with_radius(mut self, radius: f64) -> Object {
+        self.radius = radius;
+        self
+    }
+
+    /// Sets the mass from the Object's area, treating it as a flat disc of the given density.
+    ///
+    /// Call after `with_radius`.
+    pub fn with_density(mut self, density: f64) -> Object {
+        self.mass = density * std::f64::consts::PI * self.radius * self.radius;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f64) -> Object {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f64) -> Object {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Object {
+        self.color = color;
+        self
+    }
+
+    pub fn with_angular_velocity(mut self, angular_velocity: f64) -> Object {
+        self.angular_velocity = angular_velocity;
+        self.next_angular_velocity = angular_velocity;
+        self
+    }
+
+    pub fn with_body_type(mut self, body_type: BodyType) -> Object {
+        self.body_type = body_type;
+        self
+    }
+
+    /// Whether nothing in the simulation can push the Object around this step, because it is asleep, kinematic or
+    /// static.
+    pub fn is_fixed(&self) -> bool {
+        self.asleep || self.body_type != BodyType::Dynamic
+    }
+
+    /// Moment of inertia about the Object's centre, treating it as a flat disc of uniform density.
+    pub fn inertia(&self) -> f64 {
+        0.5 * self.mass * self.radius * self.radius
+    }
+
+    /// Velocity of the point on the Object's surface at `offset` from its centre, spin included.
+    pub fn surface_velocity(&self, offset: Vec2) -> Vec2 {
+        self.next_velocity
+            + Vec2 {
+                x: -offset.y,
+                y: offset.x,
+            } * self.next_angular_velocity
+    }
+
+    /// Applies Coulomb friction against a static surface touching the Object.
+    ///
+    /// `normal` points out of the surface, towards the Object, and `normal_impulse` is how much the contact just
+    /// changed the Object's speed along it. The sliding velocity of the contact point is taken away, spinning the
+    /// Object up, as far as `friction` times the normal impulse allows.
+    pub fn apply_surface_friction(&mut self, normal: Vec2, normal_impulse: f64, friction: f64) {
+        let offset = -normal * self.radius;
+        let contact_velocity = self.surface_velocity(offset);
+        let sliding = contact_velocity - normal * contact_velocity.dot(normal);
+        let sliding_speed = sliding.len();
+        if sliding_speed == 0.0 {
+            return;
+        }
+
+        // impulse that would stop the contact point sliding, capped by Coulomb's law
+        let tangent = sliding * (1.0 / sliding_speed);
+        let (w, iw) = (1.0 / self.mass, 1.0 / self.inertia());
+        let arm = offset.cross(tangent);
+        let impulse =
+            (sliding_speed / (w + arm * arm * iw)).min(friction * normal_impulse.abs() * self.mass);
+
+        self.next_velocity -= tangent * (impulse * w);
+        self.next_angular_velocity -= arm * impulse * iw;
+    }
+
+    /// Wakes the Object up, starting its rest over.
+    pub fn wake(&mut self) {
+        self.asleep = false;
+        self.rest_time = 0.0;
+    }
+
+    /// Moves the Object by `offset` without it having travelled there, e.g. when wrapping around the bounds.
+    pub fn translate(&mut self, offset: Vec2) {
+        self.position += offset;
+        self.next_position += offset;
+        self.previous_position += offset;
+    }
+
+    /// Position between the last two steps, `alpha` of the way from `previous_position` to `position`.
+    pub fn interpolated_position(&self, alpha: f64) -> Vec2 {
+        self.previous_position + (self.position - self.previous_position) * alpha
+    }
+
+    /// Angle between the last two steps, `alpha` of the way from `previous_angle` to `angle`.
+    pub fn interpolated_angle(&self, alpha: f64) -> f64 {
+        self.previous_angle + (self.angle - self.previous_angle) * alpha
+    }
 }
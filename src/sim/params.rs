@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use super::vec2::Vec2;
+
+/// Physical constants used by a `Solver`.
+///
+/// Every field can be changed between steps.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhysicsParams {
+    /// Gravitational field used by `ForceModel::UniformField`.
+    /// Measured in pixels per second squared
+    pub gravity: Vec2,
+
+    /// Bounce constant of the walls (aka deflective efficacy).
+    ///
+    /// Represents how much energy is deflected off a wall back into the ball. Combined with each object's own
+    /// `restitution` using `object::combine`.
+    pub restitution: f64,
+
+    /// Friction constant of the walls.
+    ///
+    /// Coulomb coefficient of friction between a wall and a ball touching it, which turns sliding into spin.
+    /// Combined with each object's own `friction` using `object::combine`.
+    pub friction: f64,
+
+    /// Air drag.
+    ///
+    /// Rate at which objects slow down regardless of contact: with no other forces, velocity decays as
+    /// `e^(-air_drag * t)`. `0.0` is a vacuum.
+    pub air_drag: f64,
+
+    /// How far inside the bounds an object is put back after hitting a wall.
+    /// Measured in pixels
+    pub wall_offset: f64,
+
+    /// Speed along the contact normal below which nothing bounces, so that objects settle into resting contact
+    /// instead of jittering on the spot.
+    /// Measured in pixels per second
+    pub resting_speed: f64,
+
+    /// Whether objects that have come to rest stop being simulated until something hits them.
+    ///
+    /// Only happens under `ForceModel::UniformField`. Sleeping objects don't notice changes to gravity, the bounds or
+    /// the colliders: call `Solver::wake_all` after making any.
+    pub allow_sleep: bool,
+
+    /// Speed below which an object counts as resting.
+    /// Measured in pixels per second
+    pub sleep_speed: f64,
+
+    /// How long an object has to rest before it falls asleep.
+    /// Measured in seconds
+    pub sleep_time: f64,
+}
+
+impl Default for PhysicsParams {
+    fn default() -> PhysicsParams {
+        PhysicsParams {
+            gravity: Vec2 { x: 0.0, y: 1000.0 },
+            // bounces default objects off the walls with a combined constant of 0.6
+            restitution: 0.40,
+            friction: 0.3,
+            air_drag: 0.0,
+            wall_offset: 0.05,
+            // a few steps' worth of the speed gained from gravity, so objects resting on the floor or on each
+            // other stop instead of bouncing on the spot
+            resting_speed: 50.0,
+            allow_sleep: true,
+            sleep_speed: 5.0,
+            sleep_time: 0.5,
+        }
+    }
+}
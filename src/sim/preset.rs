@@ -0,0 +1,190 @@
+//! Ready-made position-based bodies built out of small objects held together by constraints: soft bodies, cloth and
+//! chains.
+
+use std::f64::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use super::arena::ObjectId;
+use super::constraint::{Constraint, Link};
+use super::object::{BodyType, Object};
+use super::softbody::SoftBody;
+use super::solver::Solver;
+use super::vec2::Vec2;
+
+/// Radius of the objects presets are made of.
+/// Measured in pixels
+pub const PARTICLE_RADIUS: f64 = 4.0;
+
+/// Mass of the objects presets are made of.
+pub const PARTICLE_MASS: f64 = 1.0;
+
+/// Stiffness and damping of the springs around the rim of a soft body.
+const SOFT_BODY_STIFFNESS: f64 = 200.0;
+const SOFT_BODY_DAMPING: f64 = 1.0;
+
+/// A body made of many objects, spawned all at once.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Preset {
+    /// A ring of `count` objects around a circle of `radius`, linked to their neighbours by springs and keeping the
+    /// area inside at `pressure` times its starting area.
+    SoftBody {
+        radius: f64,
+        count: usize,
+        pressure: f64,
+    },
+
+    /// A grid of `columns` by `rows` objects `spacing` apart, linked to their neighbours, with its top row pinned in
+    /// place. Spawned with its top left corner at the given position.
+    Cloth {
+        columns: usize,
+        rows: usize,
+        spacing: f64,
+    },
+
+    /// `links` objects `spacing` apart hanging down in a line, each linked to the next, with the top one pinned in
+    /// place.
+    Chain { links: usize, spacing: f64 },
+}
+
+impl Preset {
+    /// One of each preset, at a size that fits the default window, in the order the app cycles through them.
+    pub fn defaults() -> [Preset; 3] {
+        [
+            Preset::SoftBody {
+                radius: 40.0,
+                count: 16,
+                pressure: 1.0,
+            },
+            Preset::Cloth {
+                columns: 12,
+                rows: 8,
+                spacing: 15.0,
+            },
+            Preset::Chain {
+                links: 12,
+                spacing: 15.0,
+            },
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::SoftBody { .. } => "soft body",
+            Preset::Cloth { .. } => "cloth",
+            Preset::Chain { .. } => "chain",
+        }
+    }
+
+    /// Adds the preset to `solver` at `position`, with every object that isn't pinned moving at `velocity`.
+    ///
+    /// Returns the ids of the new objects.
+    pub fn spawn(&self, solver: &mut Solver, position: Vec2, velocity: Vec2) -> Vec<ObjectId> {
+        let particle = |solver: &mut Solver, offset: Vec2, pinned: bool| {
+            let velocity = if pinned {
+                Vec2 { x: 0.0, y: 0.0 }
+            } else {
+                velocity
+            };
+            solver.add(
+                Object::new(position + offset, velocity, PARTICLE_MASS)
+                    .with_radius(PARTICLE_RADIUS)
+                    .with_body_type(if pinned {
+                        BodyType::Static
+                    } else {
+                        BodyType::Dynamic
+                    }),
+            )
+        };
+        // links two objects at their current distance, rigidly or with a spring
+        let link = |solver: &mut Solver, a: ObjectId, b: ObjectId, spring: bool| {
+            let length = match (solver.get(a), solver.get(b)) {
+                (Some(a), Some(b)) => Vec2::dist_scalar(a.position, b.position),
+                _ => return,
+            };
+            let link = if spring {
+                Link::Spring {
+                    rest_length: length,
+                    stiffness: SOFT_BODY_STIFFNESS,
+                    damping: SOFT_BODY_DAMPING,
+                }
+            } else {
+                Link::Distance { length }
+            };
+            solver.connect(Constraint::new(a, b, link));
+        };
+
+        match *self {
+            Preset::SoftBody {
+                radius,
+                count,
+                pressure,
+            } => {
+                let ring: Vec<ObjectId> = (0..count)
+                    .map(|idx| {
+                        let angle = TAU * idx as f64 / count as f64;
+                        let offset = Vec2 {
+                            x: angle.cos(),
+                            y: angle.sin(),
+                        } * radius;
+                        particle(solver, offset, false)
+                    })
+                    .collect();
+
+                for idx in 0..ring.len() {
+                    link(solver, ring[idx], ring[(idx + 1) % ring.len()], true);
+                }
+                if let Some(body) = SoftBody::new(ring.clone(), &solver.objects, pressure) {
+                    solver.soft_bodies.push(body);
+                }
+                ring
+            }
+            Preset::Cloth {
+                columns,
+                rows,
+                spacing,
+            } => {
+                let grid: Vec<ObjectId> = (0..rows)
+                    .flat_map(|row| (0..columns).map(move |column| (row, column)))
+                    .map(|(row, column)| {
+                        let offset = Vec2 {
+                            x: column as f64 * spacing,
+                            y: row as f64 * spacing,
+                        };
+                        particle(solver, offset, row == 0)
+                    })
+                    .collect();
+
+                for row in 0..rows {
+                    for column in 0..columns {
+                        let id = grid[row * columns + column];
+                        if column + 1 < columns {
+                            link(solver, id, grid[row * columns + column + 1], false);
+                        }
+                        if row + 1 < rows {
+                            link(solver, id, grid[(row + 1) * columns + column], false);
+                        }
+                    }
+                }
+                grid
+            }
+            Preset::Chain { links, spacing } => {
+                let chain: Vec<ObjectId> = (0..links)
+                    .map(|idx| {
+                        let offset = Vec2 {
+                            x: 0.0,
+                            y: idx as f64 * spacing,
+                        };
+                        particle(solver, offset, idx == 0)
+                    })
+                    .collect();
+
+                for pair in chain.windows(2) {
+                    link(solver, pair[0], pair[1], false);
+                }
+                chain
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use super::object::Object;
+use super::vec2::Vec2;
+
+/// Depth after which a leaf stops splitting, so that coincident objects don't recurse forever.
+const MAX_DEPTH: usize = 32;
+
+/// A square region of the quadtree.
+#[derive(Debug, Clone)]
+struct Node {
+    /// Top left corner of the region.
+    min: Vec2,
+
+    /// Side length of the region.
+    size: f64,
+
+    /// Total mass of every object in the region.
+    mass: f64,
+
+    /// Centre of mass of every object in the region.
+    center_of_mass: Vec2,
+
+    /// Indices of the four child nodes, if the region has been split.
+    children: Option<[usize; 4]>,
+
+    /// Indices of the objects in the region, if it is a leaf.
+    objects: Vec<usize>,
+}
+
+impl Node {
+    fn new(min: Vec2, size: f64) -> Node {
+        Node {
+            min,
+            size,
+            mass: 0.0,
+            center_of_mass: Vec2 { x: 0.0, y: 0.0 },
+            children: None,
+            objects: vec![],
+        }
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.min.x + self.size
+            && point.y >= self.min.y
+            && point.y <= self.min.y + self.size
+    }
+
+    /// Which of the four children `point` falls into.
+    fn quadrant(&self, point: Vec2) -> usize {
+        let half = self.size / 2.0;
+        let right = point.x >= self.min.x + half;
+        let bottom = point.y >= self.min.y + half;
+
+        (right as usize) | ((bottom as usize) << 1)
+    }
+}
+
+/// Barnes–Hut quadtree over the `next_position` of a set of objects.
+///
+/// Distant groups of objects are approximated by a single point mass at their centre of mass, which brings the
+/// cost of computing gravitational accelerations down from O(n²) to O(n log n).
+#[derive(Debug, Clone)]
+pub struct QuadTree {
+    nodes: Vec<Node>,
+}
+
+impl QuadTree {
+    pub fn new(objects: &[Object]) -> QuadTree {
+        // smallest square containing every object
+        let mut min = Vec2 {
+            x: f64::INFINITY,
+            y: f64::INFINITY,
+        };
+        let mut max = Vec2 {
+            x: f64::NEG_INFINITY,
+            y: f64::NEG_INFINITY,
+        };
+        for i in objects {
+            min.x = min.x.min(i.next_position.x);
+            min.y = min.y.min(i.next_position.y);
+            max.x = max.x.max(i.next_position.x);
+            max.y = max.y.max(i.next_position.y);
+        }
+        let size = (max.x - min.x).max(max.y - min.y).max(1.0);
+
+        let mut tree = QuadTree { nodes: vec![] };
+        if objects.is_empty() {
+            return tree;
+        }
+
+        tree.nodes.push(Node::new(min, size));
+        for idx in 0..objects.len() {
+            tree.insert(objects, 0, idx, 0);
+        }
+        tree.summarize(objects, 0);
+
+        tree
+    }
+
+    fn insert(&mut self, objects: &[Object], node: usize, idx: usize, depth: usize) {
+        if let Some(children) = self.nodes[node].children {
+            let quadrant = self.nodes[node].quadrant(objects[idx].next_position);
+            self.insert(objects, children[quadrant], idx, depth + 1);
+            return;
+        }
+
+        self.nodes[node].objects.push(idx);
+        if self.nodes[node].objects.len() == 1 || depth >= MAX_DEPTH {
+            return;
+        }
+
+        // split the leaf and push its objects down a level
+        let (min, half) = (self.nodes[node].min, self.nodes[node].size / 2.0);
+        let first = self.nodes.len();
+        for quadrant in 0..4 {
+            let offset = Vec2 {
+                x: (quadrant & 1) as f64 * half,
+                y: (quadrant >> 1) as f64 * half,
+            };
+            self.nodes.push(Node::new(min + offset, half));
+        }
+        self.nodes[node].children = Some([first, first + 1, first + 2, first + 3]);
+
+        for jdx in std::mem::take(&mut self.nodes[node].objects) {
+            self.insert(objects, node, jdx, depth);
+        }
+    }
+
+    /// Fills in the mass and centre of mass of `node` and everything below it.
+    fn summarize(&mut self, objects: &[Object], node: usize) {
+        let mut mass = 0.0;
+        let mut moment = Vec2 { x: 0.0, y: 0.0 };
+
+        if let Some(children) = self.nodes[node].children {
+            for child in children {
+                self.summarize(objects, child);
+                mass += self.nodes[child].mass;
+                moment += self.nodes[child].center_of_mass * self.nodes[child].mass;
+            }
+        } else {
+            for &idx in &self.nodes[node].objects {
+                mass += objects[idx].mass;
+                moment += objects[idx].next_position * objects[idx].mass;
+            }
+        }
+
+        self.nodes[node].mass = mass;
+        if mass != 0.0 {
+            self.nodes[node].center_of_mass = moment * (1.0 / mass);
+        }
+    }
+
+    /// Approximate acceleration of object `idx` due to every other object.
+    ///
+    /// A region is treated as a single point mass when `size / distance < theta`. A `theta` of `0.0` reproduces
+    /// the exact pairwise sum; around `0.5` is the usual trade-off between speed and accuracy.
+    pub fn acceleration(
+        &self,
+        objects: &[Object],
+        idx: usize,
+        g: f64,
+        softening: f64,
+        theta: f64,
+    ) -> Vec2 {
+        let mut acceleration = Vec2 { x: 0.0, y: 0.0 };
+        if self.nodes.is_empty() {
+            return acceleration;
+        }
+
+        let position = objects[idx].next_position;
+        let pull = |towards: Vec2, mass: f64| {
+            let r = towards - position;
+            r * (g * mass * (r.dot(r) + softening * softening).powf(-1.5))
+        };
+
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.mass == 0.0 {
+                continue;
+            }
+
+            match node.children {
+                None => {
+                    for &jdx in node.objects.iter().filter(|&&jdx| jdx != idx) {
+                        acceleration += pull(objects[jdx].next_position, objects[jdx].mass);
+                    }
+                }
+                Some(children) => {
+                    let distance = Vec2::dist_scalar(position, node.center_of_mass);
+
+                    // never approximate a region containing the object itself
+                    if !node.contains(position) && node.size < theta * distance {
+                        acceleration += pull(node.center_of_mass, node.mass);
+                    } else {
+                        stack.extend(children);
+                    }
+                }
+            }
+        }
+
+        acceleration
+    }
+}
+
+/// Acceleration of every object due to every other object, approximated with a Barnes–Hut quadtree.
+pub fn barnes_hut_accelerations(
+    objects: &[Object],
+    g: f64,
+    softening: f64,
+    theta: f64,
+) -> Vec<Vec2> {
+    let tree = QuadTree::new(objects);
+
+    (0..objects.len())
+        .map(|idx| tree.acceleration(objects, idx, g, softening, theta))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::force::{
+        mutual_accelerations, GRAVITATIONAL_CONSTANT, OPENING_ANGLE, SOFTENING,
+    };
+
+    /// A reproducible cloud of `count` objects of varying mass, bunched up towards the middle of an 800 by 800 box.
+    fn cluster(count: usize) -> Vec<Object> {
+        // xorshift, so the cluster is the same every run
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+
+        (0..count)
+            .map(|_| {
+                let (radius, angle) = (
+                    400.0 * random() * random(),
+                    std::f64::consts::TAU * random(),
+                );
+                let position = Vec2 {
+                    x: 400.0 + radius * angle.cos(),
+                    y: 400.0 + radius * angle.sin(),
+                };
+                Object::new(position, Vec2 { x: 0.0, y: 0.0 }, 1.0 + 19.0 * random())
+            })
+            .collect()
+    }
+
+    /// Largest error of `approximate` against `exact`, relative to the average size of the exact accelerations.
+    fn relative_error(approximate: &[Vec2], exact: &[Vec2]) -> f64 {
+        let scale = exact.iter().map(|a| a.len()).sum::<f64>() / exact.len() as f64;
+        approximate
+            .iter()
+            .zip(exact)
+            .map(|(a, b)| (*a - *b).len())
+            .fold(0.0, f64::max)
+            / scale
+    }
+
+    #[test]
+    fn barnes_hut_is_close_to_the_pairwise_sum() {
+        let objects = cluster(500);
+        let exact = mutual_accelerations(&objects, GRAVITATIONAL_CONSTANT, SOFTENING);
+        let approximate =
+            barnes_hut_accelerations(&objects, GRAVITATIONAL_CONSTANT, SOFTENING, OPENING_ANGLE);
+
+        let error = relative_error(&approximate, &exact);
+        assert!(error < 0.05, "relative error {}", error);
+    }
+
+    #[test]
+    fn barnes_hut_with_no_opening_angle_is_exact() {
+        let objects = cluster(500);
+        let exact = mutual_accelerations(&objects, GRAVITATIONAL_CONSTANT, SOFTENING);
+        let approximate =
+            barnes_hut_accelerations(&objects, GRAVITATIONAL_CONSTANT, SOFTENING, 0.0);
+
+        // only the order the pulls are added up in differs
+        let error = relative_error(&approximate, &exact);
+        assert!(error < 1e-12, "relative error {}", error);
+    }
+}
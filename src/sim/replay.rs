@@ -0,0 +1,388 @@
+//! Recording a session as a timeline of steps and events, and playing it back exactly.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::boundary::Boundaries;
+use super::collider::Collider;
+use super::force::ForceModel;
+use super::integrator;
+use super::object::{BodyType, Object};
+use super::preset::Preset;
+use super::scene::{Scene, SceneBounds, SceneConstraint, SceneError, SceneObject};
+use super::solver::Solver;
+use super::vec2::Vec2;
+
+/// Version of the recording format written by this build. Files with a newer version are rejected.
+pub const RECORDING_VERSION: u32 = 1;
+
+/// Something that happened to a solver between steps.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Event {
+    /// An object was added.
+    Spawn { object: SceneObject },
+
+    /// A static collider was added.
+    AddCollider { collider: Collider },
+
+    /// A preset body was spawned, as by `Preset::spawn`.
+    SpawnPreset {
+        preset: Preset,
+        position: Vec2,
+        velocity: Vec2,
+    },
+
+    /// Two objects were linked, by their index in `Solver::objects` at the time.
+    AddConstraint { constraint: SceneConstraint },
+
+    /// An object, by its index in `Solver::objects` at the time, was made dynamic, kinematic or static.
+    SetBodyType { object: usize, body_type: BodyType },
+
+    /// An object, by its index in `Solver::objects` at the time, was set moving at `velocity`, e.g. a kinematic one
+    /// steered by the mouse.
+    SetVelocity { object: usize, velocity: Vec2 },
+
+    /// Every object and collider was removed.
+    Clear,
+
+    /// The force model was changed.
+    SetForceModel { force_model: ForceModel },
+
+    /// The bounds were moved, e.g. to follow the window when it was resized.
+    SetBounds { bounds: SceneBounds },
+
+    /// The mode of the sides of the bounds was changed.
+    SetBoundaries { boundaries: Boundaries },
+
+    /// The integrator was changed, by name.
+    SetIntegrator { name: String },
+
+    /// A scene was loaded, replacing everything.
+    LoadScene { scene: Box<Scene> },
+
+    /// The time scale was changed. Only affects the `dt` of later steps, which are recorded anyway.
+    SetTimeScale { time_scale: f64 },
+
+    /// A display option was toggled. Doesn't affect the simulation.
+    ToggleDisplay { option: String },
+}
+
+impl Event {
+    /// Applies the event to `solver`.
+    pub fn apply(&self, solver: &mut Solver) {
+        match self {
+            Event::Spawn { object } => {
+                solver.add(Object::from(object));
+            }
+            Event::AddCollider { collider } => {
+                solver.colliders.push(collider.clone());
+                solver.wake_all();
+            }
+            Event::SpawnPreset {
+                preset,
+                position,
+                velocity,
+            } => {
+                preset.spawn(solver, *position, *velocity);
+            }
+            Event::AddConstraint { constraint } => {
+                if let Some(constraint) = constraint.to_constraint(solver) {
+                    solver.connect(constraint);
+                }
+            }
+            Event::SetBodyType { object, body_type } => {
+                if let Some(id) = solver.objects.id_at(*object) {
+                    solver.set_body_type(id, *body_type);
+                }
+            }
+            Event::SetVelocity { object, velocity } => {
+                if let Some(id) = solver.objects.id_at(*object) {
+                    solver.wake(id);
+                    if let Some(i) = solver.get_mut(id) {
+                        i.velocity = *velocity;
+                        i.next_velocity = *velocity;
+                    }
+                }
+            }
+            Event::Clear => {
+                solver.clear();
+                solver.colliders.clear();
+            }
+            Event::SetForceModel { force_model } => solver.force_model = *force_model,
+            Event::SetBounds { bounds } => {
+                solver.bound_left = bounds.left;
+                solver.bound_right = bounds.right;
+                solver.bound_top = bounds.top;
+                solver.bound_bottom = bounds.bottom;
+                solver.wake_all();
+            }
+            Event::SetBoundaries { boundaries } => {
+                solver.boundaries = *boundaries;
+                solver.wake_all();
+            }
+            Event::SetIntegrator { name } => {
+                if let Some(integrator) = integrator::by_name(name) {
+                    solver.integrator = integrator;
+                }
+            }
+            Event::LoadScene { scene } => *solver = scene.to_solver(),
+            Event::SetTimeScale { .. } | Event::ToggleDisplay { .. } => {}
+        }
+    }
+}
+
+/// One entry of a recording's timeline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Entry {
+    /// `Solver::step` was called with `dt`.
+    Step { dt: f64 },
+
+    /// An event was applied.
+    Event { event: Event },
+}
+
+/// A recorded session: the state it started from, and every step and event in the order they happened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+
+    /// State of the solver when recording started.
+    pub initial: Scene,
+
+    pub timeline: Vec<Entry>,
+
+    /// `Solver::state_hash` when recording stopped.
+    pub final_hash: u64,
+}
+
+/// Why a recording couldn't be loaded or played back.
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The file isn't valid JSON, or is missing required fields.
+    Json(serde_json::Error),
+
+    /// The file was written by a newer version of the format.
+    UnsupportedVersion(u32),
+
+    /// The initial scene is invalid.
+    Scene(SceneError),
+
+    /// The scene loaded by the timeline entry at this index is invalid.
+    InvalidScene { entry: usize, error: SceneError },
+
+    /// The object spawned by the timeline entry at this index doesn't have a positive mass and radius.
+    InvalidSpawn(usize),
+
+    /// Playing the recording back ended in a different state than it was recorded with.
+    HashMismatch { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Json(e) => write!(f, "{}", e),
+            ReplayError::UnsupportedVersion(v) => write!(
+                f,
+                "recording version {} is newer than the supported version {}",
+                v, RECORDING_VERSION
+            ),
+            ReplayError::Scene(e) => write!(f, "invalid initial scene: {}", e),
+            ReplayError::InvalidScene { entry, error } => {
+                write!(f, "invalid scene loaded by entry {}: {}", entry, error)
+            }
+            ReplayError::InvalidSpawn(entry) => write!(
+                f,
+                "object spawned by entry {} must have a positive mass and radius",
+                entry
+            ),
+            ReplayError::HashMismatch { expected, actual } => write!(
+                f,
+                "replay diverged: expected state hash {:016x}, got {:016x}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(value: serde_json::Error) -> Self {
+        ReplayError::Json(value)
+    }
+}
+
+impl Recording {
+    /// Starts a recording from the current state of `solver`.
+    ///
+    /// `solver` is reset to the captured state, so that playback starts from exactly the same place: anything a
+    /// scene doesn't store (such as time left over from the last `step`) is dropped.
+    pub fn start(solver: &mut Solver) -> Recording {
+        let initial = Scene::from_solver(solver);
+        *solver = initial.to_solver();
+
+        Recording {
+            version: RECORDING_VERSION,
+            initial,
+            timeline: vec![],
+            final_hash: solver.state_hash(),
+        }
+    }
+
+    /// Records a call to `Solver::step`.
+    pub fn step(&mut self, dt: f64) {
+        self.timeline.push(Entry::Step { dt });
+    }
+
+    /// Records an event.
+    pub fn event(&mut self, event: Event) {
+        self.timeline.push(Entry::Event { event });
+    }
+
+    /// Stops recording, remembering the final state of `solver` to check playback against.
+    pub fn finish(&mut self, solver: &Solver) {
+        self.final_hash = solver.state_hash();
+    }
+
+    pub fn from_json(json: &str) -> Result<Recording, ReplayError> {
+        let recording: Recording = serde_json::from_str(json)?;
+
+        if recording.version > RECORDING_VERSION {
+            return Err(ReplayError::UnsupportedVersion(recording.version));
+        }
+        recording.initial.validate().map_err(ReplayError::Scene)?;
+        for (entry, event) in recording.timeline.iter().enumerate() {
+            match event {
+                Entry::Event {
+                    event: Event::LoadScene { scene },
+                } => scene
+                    .validate()
+                    .map_err(|error| ReplayError::InvalidScene { entry, error })?,
+                Entry::Event {
+                    event: Event::Spawn { object },
+                } if !object.is_valid() => return Err(ReplayError::InvalidSpawn(entry)),
+                _ => {}
+            }
+        }
+
+        Ok(recording)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("recordings only contain plain data")
+    }
+
+    /// Plays the whole recording back and checks it ends in the recorded state.
+    pub fn replay(&self) -> Result<Solver, ReplayError> {
+        let mut solver = self.initial.to_solver();
+        for entry in &self.timeline {
+            entry.apply(&mut solver);
+        }
+
+        let actual = solver.state_hash();
+        if actual != self.final_hash {
+            return Err(ReplayError::HashMismatch {
+                expected: self.final_hash,
+                actual,
+            });
+        }
+
+        Ok(solver)
+    }
+}
+
+impl Entry {
+    /// Applies the entry to `solver`: steps it, or applies the event.
+    pub fn apply(&self, solver: &mut Solver) {
+        match self {
+            Entry::Step { dt } => {
+                solver.step(*dt);
+            }
+            Entry::Event { event } => event.apply(solver),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec2;
+
+    /// A recording of a few balls thrown into an empty box, stepped with uneven `dt`s like a real frame rate.
+    fn throw_balls() -> Recording {
+        let mut solver = Solver::new(0.0, 800.0, 0.0, 400.0);
+        let mut recording = Recording::start(&mut solver);
+
+        for (k, dt) in [0.016, 0.021, 0.007, 0.033, 0.016, 0.05]
+            .into_iter()
+            .enumerate()
+        {
+            let object = Object::new(vec2!(100.0 + 50.0 * k as f64, 100), vec2!(200, -50), 10.0);
+            let event = Event::Spawn {
+                object: SceneObject::from(&object),
+            };
+            event.apply(&mut solver);
+            recording.event(event);
+
+            for _ in 0..10 {
+                solver.step(dt);
+                recording.step(dt);
+            }
+        }
+        recording.finish(&solver);
+
+        recording
+    }
+
+    #[test]
+    fn a_recording_plays_back_the_same_after_a_round_trip_through_json() {
+        let recording = Recording::from_json(&throw_balls().to_json()).unwrap();
+        let solver = recording.replay().unwrap();
+        assert_eq!(solver.objects.len(), 6);
+    }
+
+    #[test]
+    fn recordings_spawning_invalid_objects_or_loading_invalid_scenes_are_rejected() {
+        let mut recording = throw_balls();
+        if let Entry::Event {
+            event: Event::Spawn { object },
+        } = &mut recording.timeline[11]
+        {
+            object.radius = 0.0;
+        }
+        let result = Recording::from_json(&recording.to_json());
+        assert!(
+            matches!(result, Err(ReplayError::InvalidSpawn(11))),
+            "{:?}",
+            result
+        );
+
+        let mut scene = throw_balls().initial;
+        scene.fixed_dt = -1.0;
+        let mut recording = throw_balls();
+        recording.event(Event::LoadScene {
+            scene: Box::new(scene.clone()),
+        });
+        let result = Recording::from_json(&recording.to_json());
+        assert!(
+            matches!(result, Err(ReplayError::InvalidScene { entry: 66, .. })),
+            "{:?}",
+            result
+        );
+
+        recording.initial = scene;
+        let result = Recording::from_json(&recording.to_json());
+        assert!(
+            matches!(
+                result,
+                Err(ReplayError::Scene(SceneError::InvalidFixedDt(_)))
+            ),
+            "{:?}",
+            result
+        );
+    }
+}
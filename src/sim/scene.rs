@@ -0,0 +1,642 @@
+//! Saving and loading the whole state of a `Solver` as JSON.
+//!
+//! The format is documented in `docs/scene-format.md`.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::arena::ObjectId;
+use super::boundary::Boundaries;
+use super::broadphase::{BroadPhase, MIN_CELL_SIZE};
+use super::collider::Collider;
+use super::constraint::{Constraint, Link};
+use super::force::ForceModel;
+use super::integrator::{self, Integrator, SemiImplicitEuler};
+use super::object::{
+    BodyType, Object, OBJECT_COLOR, OBJECT_FRICTION, OBJECT_RADIUS, OBJECT_RESTITUTION,
+};
+use super::params::PhysicsParams;
+use super::softbody::SoftBody;
+use super::solver::{Solver, FIXED_DT};
+use super::vec2::Vec2;
+
+/// Version of the scene format written by this build. Files with a newer version are rejected.
+pub const SCENE_VERSION: u32 = 1;
+
+/// Complete state of a simulation, as stored in a scene file.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "bounds": { "left": 0, "right": 800, "top": 0, "bottom": 400 },
+///   "objects": [
+///     { "position": { "x": 100, "y": 100 }, "velocity": { "x": 200, "y": 0 }, "mass": 10 }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+
+    pub bounds: SceneBounds,
+
+    /// What happens at each side of `bounds`.
+    #[serde(default)]
+    pub boundaries: Boundaries,
+
+    #[serde(default)]
+    pub params: PhysicsParams,
+
+    #[serde(default)]
+    pub force_model: ForceModel,
+
+    /// Name of the integrator, as returned by `Integrator::name`.
+    #[serde(default = "default_integrator")]
+    pub integrator: String,
+
+    #[serde(default)]
+    pub broad_phase: BroadPhase,
+
+    #[serde(default = "default_fixed_dt")]
+    pub fixed_dt: f64,
+
+    #[serde(default)]
+    pub objects: Vec<SceneObject>,
+
+    /// Static obstacles, as in `Solver::colliders`.
+    #[serde(default)]
+    pub colliders: Vec<Collider>,
+
+    /// Links between objects, as in `Solver::constraints`.
+    #[serde(default)]
+    pub constraints: Vec<SceneConstraint>,
+
+    /// Rings of objects keeping their area, as in `Solver::soft_bodies`.
+    #[serde(default)]
+    pub soft_bodies: Vec<SceneSoftBody>,
+}
+
+/// The walls of a scene.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneBounds {
+    pub left: f64,
+    pub right: f64,
+    pub top: f64,
+    pub bottom: f64,
+}
+
+/// An object in a scene. Everything but `position`, `velocity` and `mass` is optional.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneObject {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub mass: f64,
+
+    #[serde(default = "default_radius")]
+    pub radius: f64,
+
+    #[serde(default = "default_restitution")]
+    pub restitution: f64,
+
+    #[serde(default = "default_friction")]
+    pub friction: f64,
+
+    #[serde(default = "default_color")]
+    pub color: [f32; 4],
+
+    #[serde(default)]
+    pub angle: f64,
+
+    #[serde(default)]
+    pub angular_velocity: f64,
+
+    #[serde(default)]
+    pub asleep: bool,
+
+    #[serde(default)]
+    pub rest_time: f64,
+
+    #[serde(default)]
+    pub body_type: BodyType,
+}
+
+/// A constraint in a scene, linking two objects by their index in `Scene::objects`.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneConstraint {
+    pub a: usize,
+    pub b: usize,
+
+    #[serde(flatten)]
+    pub link: Link,
+}
+
+impl SceneConstraint {
+    /// Describes `constraint` by the index of its objects in `solver`, or `None` if either is gone.
+    pub fn from_constraint(constraint: &Constraint, solver: &Solver) -> Option<SceneConstraint> {
+        Some(SceneConstraint {
+            a: solver.objects.index_of(constraint.a)?,
+            b: solver.objects.index_of(constraint.b)?,
+            link: constraint.link,
+        })
+    }
+
+    /// The constraint between the objects at its indices in `solver`, or `None` if either doesn't exist.
+    pub fn to_constraint(&self, solver: &Solver) -> Option<Constraint> {
+        Some(Constraint::new(
+            solver.objects.id_at(self.a)?,
+            solver.objects.id_at(self.b)?,
+            self.link,
+        ))
+    }
+}
+
+/// A soft body in a scene, with its ring given by the index of each object in `Scene::objects`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneSoftBody {
+    pub ring: Vec<usize>,
+    pub rest_area: f64,
+
+    #[serde(default = "default_pressure")]
+    pub pressure: f64,
+}
+
+impl SceneSoftBody {
+    /// Describes `body` by the index of its objects in `solver`, or `None` if any is gone.
+    pub fn from_soft_body(body: &SoftBody, solver: &Solver) -> Option<SceneSoftBody> {
+        Some(SceneSoftBody {
+            ring: body
+                .ring
+                .iter()
+                .map(|&id| solver.objects.index_of(id))
+                .collect::<Option<Vec<usize>>>()?,
+            rest_area: body.rest_area,
+            pressure: body.pressure,
+        })
+    }
+
+    /// The soft body made of the objects at its indices in `solver`, or `None` if any doesn't exist.
+    pub fn to_soft_body(&self, solver: &Solver) -> Option<SoftBody> {
+        Some(SoftBody {
+            ring: self
+                .ring
+                .iter()
+                .map(|&idx| solver.objects.id_at(idx))
+                .collect::<Option<Vec<ObjectId>>>()?,
+            rest_area: self.rest_area,
+            pressure: self.pressure,
+        })
+    }
+}
+
+fn default_integrator() -> String {
+    SemiImplicitEuler.name().to_string()
+}
+
+fn default_fixed_dt() -> f64 {
+    FIXED_DT
+}
+
+fn default_radius() -> f64 {
+    OBJECT_RADIUS
+}
+
+fn default_restitution() -> f64 {
+    OBJECT_RESTITUTION
+}
+
+fn default_friction() -> f64 {
+    OBJECT_FRICTION
+}
+
+fn default_color() -> [f32; 4] {
+    OBJECT_COLOR
+}
+
+fn default_pressure() -> f64 {
+    1.0
+}
+
+/// Why a scene couldn't be loaded.
+#[derive(Debug)]
+pub enum SceneError {
+    /// The file isn't valid JSON, or is missing required fields.
+    Json(serde_json::Error),
+
+    /// The file was written by a newer version of the format.
+    UnsupportedVersion(u32),
+
+    /// The file names an integrator that doesn't exist.
+    UnknownIntegrator(String),
+
+    /// A constraint or soft body links an object index past the end of the objects.
+    UnknownObject(usize),
+
+    /// The bounds aren't finite, or `left` isn't left of `right` or `top` isn't above `bottom`.
+    InvalidBounds(SceneBounds),
+
+    /// The force model has a constant that isn't a finite number.
+    InvalidForceModel(ForceModel),
+
+    /// The uniform grid broad phase has a `cell_size` that isn't finite or is smaller than `MIN_CELL_SIZE`.
+    InvalidCellSize(f64),
+
+    /// `fixed_dt` isn't a positive number of seconds.
+    InvalidFixedDt(f64),
+
+    /// The object at this index doesn't have a positive mass.
+    InvalidMass(usize),
+
+    /// The object at this index doesn't have a positive radius.
+    InvalidRadius(usize),
+
+    /// The collider at this index is a segment with both ends in the same place, or a polygon with fewer than
+    /// three corners or that isn't convex.
+    InvalidCollider(usize),
+
+    /// The constraint at this index links an object to itself, or has a negative or non-finite length, stiffness or
+    /// damping.
+    InvalidConstraint(usize),
+
+    /// The soft body at this index has fewer than three objects, or a rest area or pressure that isn't a finite
+    /// number.
+    InvalidSoftBody(usize),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Json(e) => write!(f, "{}", e),
+            SceneError::UnsupportedVersion(v) => write!(
+                f,
+                "scene version {} is newer than the supported version {}",
+                v, SCENE_VERSION
+            ),
+            SceneError::UnknownIntegrator(name) => write!(f, "unknown integrator {}", name),
+            SceneError::UnknownObject(idx) => write!(f, "link to unknown object {}", idx),
+            SceneError::InvalidBounds(b) => write!(
+                f,
+                "bounds must have left < right and top < bottom, not left {}, right {}, top {} and bottom {}",
+                b.left, b.right, b.top, b.bottom
+            ),
+            SceneError::InvalidForceModel(model) => {
+                write!(f, "force model constants must be finite, not {:?}", model)
+            }
+            SceneError::InvalidCellSize(size) => write!(
+                f,
+                "cell_size must be at least {}, not {}",
+                MIN_CELL_SIZE, size
+            ),
+            SceneError::InvalidFixedDt(dt) => write!(f, "fixed_dt must be positive, not {}", dt),
+            SceneError::InvalidMass(idx) => write!(f, "object {} must have a positive mass", idx),
+            SceneError::InvalidRadius(idx) => {
+                write!(f, "object {} must have a positive radius", idx)
+            }
+            SceneError::InvalidCollider(idx) => write!(
+                f,
+                "collider {} must be a segment of some length or a convex polygon with at least three corners",
+                idx
+            ),
+            SceneError::InvalidConstraint(idx) => write!(
+                f,
+                "constraint {} must link two different objects, with lengths, stiffness and damping that aren't \
+                 negative",
+                idx
+            ),
+            SceneError::InvalidSoftBody(idx) => write!(
+                f,
+                "soft body {} must have at least three objects, and a finite rest area and pressure",
+                idx
+            ),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(value: serde_json::Error) -> Self {
+        SceneError::Json(value)
+    }
+}
+
+impl SceneObject {
+    /// Whether the object has a positive mass and radius.
+    pub fn is_valid(&self) -> bool {
+        positive(self.mass) && positive(self.radius)
+    }
+}
+
+/// Whether `value` is a positive, finite number.
+fn positive(value: f64) -> bool {
+    value > 0.0 && value.is_finite()
+}
+
+impl From<&Object> for SceneObject {
+    fn from(i: &Object) -> Self {
+        SceneObject {
+            position: i.position,
+            velocity: i.velocity,
+            mass: i.mass,
+            radius: i.radius,
+            restitution: i.restitution,
+            friction: i.friction,
+            color: i.color,
+            angle: i.angle,
+            angular_velocity: i.angular_velocity,
+            asleep: i.asleep,
+            rest_time: i.rest_time,
+            body_type: i.body_type,
+        }
+    }
+}
+
+impl From<&SceneObject> for Object {
+    fn from(i: &SceneObject) -> Self {
+        Object {
+            angle: i.angle,
+            next_angle: i.angle,
+            previous_angle: i.angle,
+            asleep: i.asleep,
+            rest_time: i.rest_time,
+            body_type: i.body_type,
+            ..Object::new(i.position, i.velocity, i.mass)
+                .with_radius(i.radius)
+                .with_restitution(i.restitution)
+                .with_friction(i.friction)
+                .with_color(i.color)
+                .with_angular_velocity(i.angular_velocity)
+        }
+    }
+}
+
+impl Scene {
+    /// Captures the current state of `solver`.
+    pub fn from_solver(solver: &Solver) -> Scene {
+        Scene {
+            version: SCENE_VERSION,
+            bounds: SceneBounds {
+                left: solver.bound_left,
+                right: solver.bound_right,
+                top: solver.bound_top,
+                bottom: solver.bound_bottom,
+            },
+            boundaries: solver.boundaries,
+            params: solver.params,
+            force_model: solver.force_model,
+            integrator: solver.integrator.name().to_string(),
+            broad_phase: solver.broad_phase,
+            fixed_dt: solver.fixed_dt,
+            objects: solver.objects.iter().map(SceneObject::from).collect(),
+            colliders: solver.colliders.clone(),
+            constraints: solver
+                .constraints
+                .iter()
+                .filter_map(|c| SceneConstraint::from_constraint(c, solver))
+                .collect(),
+            soft_bodies: solver
+                .soft_bodies
+                .iter()
+                .filter_map(|body| SceneSoftBody::from_soft_body(body, solver))
+                .collect(),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Scene, SceneError> {
+        let scene: Scene = serde_json::from_str(json)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    /// Checks the scene can be simulated as is: everything `from_json` rejects.
+    pub fn validate(&self) -> Result<(), SceneError> {
+        if self.version > SCENE_VERSION {
+            return Err(SceneError::UnsupportedVersion(self.version));
+        }
+        let b = self.bounds;
+        if !(b.left < b.right
+            && b.top < b.bottom
+            && [b.left, b.right, b.top, b.bottom]
+                .iter()
+                .all(|v| v.is_finite()))
+        {
+            return Err(SceneError::InvalidBounds(b));
+        }
+        if integrator::by_name(&self.integrator).is_none() {
+            return Err(SceneError::UnknownIntegrator(self.integrator.clone()));
+        }
+        if !self.force_model.is_valid() {
+            return Err(SceneError::InvalidForceModel(self.force_model));
+        }
+        if let BroadPhase::UniformGrid { cell_size } = self.broad_phase {
+            if !self.broad_phase.is_valid() {
+                return Err(SceneError::InvalidCellSize(cell_size));
+            }
+        }
+        if !positive(self.fixed_dt) {
+            return Err(SceneError::InvalidFixedDt(self.fixed_dt));
+        }
+        if let Some(idx) = self.objects.iter().position(|i| !positive(i.mass)) {
+            return Err(SceneError::InvalidMass(idx));
+        }
+        if let Some(idx) = self.objects.iter().position(|i| !positive(i.radius)) {
+            return Err(SceneError::InvalidRadius(idx));
+        }
+        if let Some(idx) = self.colliders.iter().position(|c| !c.is_valid()) {
+            return Err(SceneError::InvalidCollider(idx));
+        }
+        if let Some(idx) = self
+            .constraints
+            .iter()
+            .flat_map(|c| [c.a, c.b])
+            .chain(
+                self.soft_bodies
+                    .iter()
+                    .flat_map(|body| body.ring.iter().copied()),
+            )
+            .find(|&idx| idx >= self.objects.len())
+        {
+            return Err(SceneError::UnknownObject(idx));
+        }
+        if let Some(idx) = self
+            .constraints
+            .iter()
+            .position(|c| c.a == c.b || !c.link.is_valid())
+        {
+            return Err(SceneError::InvalidConstraint(idx));
+        }
+        if let Some(idx) = self.soft_bodies.iter().position(|body| {
+            body.ring.len() < 3 || !body.rest_area.is_finite() || !body.pressure.is_finite()
+        }) {
+            return Err(SceneError::InvalidSoftBody(idx));
+        }
+
+        Ok(())
+    }
+
+    /// Pretty-printed JSON, to keep scene files easy to edit by hand.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scenes only contain plain data")
+    }
+
+    /// Builds a solver in the state described by the scene.
+    pub fn to_solver(&self) -> Solver {
+        let b = self.bounds;
+        let mut solver = Solver::new(b.left, b.right, b.top, b.bottom);
+
+        solver.boundaries = self.boundaries;
+        solver.params = self.params;
+        solver.force_model = self.force_model;
+        solver.integrator =
+            integrator::by_name(&self.integrator).unwrap_or_else(|| Arc::new(SemiImplicitEuler));
+        solver.broad_phase = self.broad_phase;
+        solver.fixed_dt = self.fixed_dt;
+
+        for i in &self.objects {
+            solver.add(Object::from(i));
+        }
+        solver.colliders = self.colliders.clone();
+        solver.constraints = self
+            .constraints
+            .iter()
+            .filter_map(|c| c.to_constraint(&solver))
+            .collect();
+        solver.soft_bodies = self
+            .soft_bodies
+            .iter()
+            .filter_map(|body| body.to_soft_body(&solver))
+            .collect();
+
+        solver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{json, Value};
+
+    /// Loads a valid scene, with a ball resting on a triangle, after `edit` has changed it.
+    fn load(edit: impl FnOnce(&mut Value)) -> Result<Scene, SceneError> {
+        let mut scene = json!({
+            "version": 1,
+            "bounds": { "left": 0, "right": 800, "top": 0, "bottom": 400 },
+            "objects": [{ "position": { "x": 300, "y": 210 }, "velocity": { "x": 0, "y": 0 }, "mass": 10 }],
+            "colliders": [{
+                "type": "polygon",
+                "points": [{ "x": 200, "y": 300 }, { "x": 400, "y": 300 }, { "x": 300, "y": 220 }]
+            }]
+        });
+        edit(&mut scene);
+        Scene::from_json(&scene.to_string())
+    }
+
+    #[test]
+    fn a_valid_scene_loads() {
+        assert!(load(|_| {}).is_ok());
+    }
+
+    #[test]
+    fn objects_without_a_positive_radius_are_rejected() {
+        for radius in [0.0, -10.0] {
+            let result = load(|scene| scene["objects"][0]["radius"] = json!(radius));
+            assert!(
+                matches!(result, Err(SceneError::InvalidRadius(0))),
+                "{:?}",
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn uniform_grids_with_tiny_or_negative_cells_are_rejected() {
+        for cell_size in [0.0, -40.0, 1e-9] {
+            let result = load(|scene| {
+                scene["broad_phase"] = json!({ "type": "uniform-grid", "cell_size": cell_size })
+            });
+            assert!(
+                matches!(result, Err(SceneError::InvalidCellSize(_))),
+                "{:?}",
+                result
+            );
+        }
+        assert!(load(
+            |scene| scene["broad_phase"] = json!({ "type": "uniform-grid", "cell_size": 40 })
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn polygons_with_too_few_corners_or_that_are_not_convex_are_rejected() {
+        let point = |x: f64, y: f64| json!({ "x": x, "y": y });
+        let polygons = [
+            vec![point(200.0, 300.0), point(400.0, 300.0)],
+            // an arrowhead, dented in at the bottom
+            vec![
+                point(200.0, 300.0),
+                point(300.0, 250.0),
+                point(400.0, 300.0),
+                point(300.0, 200.0),
+            ],
+            // a five-pointed star, going round twice
+            (0..5)
+                .map(|k| {
+                    let angle = k as f64 * 4.0 * std::f64::consts::PI / 5.0;
+                    point(300.0 + 50.0 * angle.sin(), 250.0 - 50.0 * angle.cos())
+                })
+                .collect(),
+        ];
+        for points in polygons {
+            let result = load(|scene| scene["colliders"][0]["points"] = json!(points));
+            assert!(
+                matches!(result, Err(SceneError::InvalidCollider(0))),
+                "{:?}",
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn force_models_with_constants_that_are_not_finite_are_rejected() {
+        assert!(!ForceModel::Mutual {
+            g: f64::NAN,
+            softening: 10.0
+        }
+        .is_valid());
+        assert!(!ForceModel::BarnesHut {
+            g: 1e5,
+            softening: 10.0,
+            theta: f64::INFINITY
+        }
+        .is_valid());
+        assert!(ForceModel::barnes_hut().is_valid());
+
+        // JSON has no way of writing infinity, and numbers too large for an `f64` don't parse
+        let json = r#"{
+            "version": 1,
+            "bounds": { "left": 0, "right": 800, "top": 0, "bottom": 400 },
+            "force_model": { "type": "mutual", "g": 1e400, "softening": 10 }
+        }"#;
+        assert!(matches!(Scene::from_json(json), Err(SceneError::Json(_))));
+    }
+
+    #[test]
+    fn inverted_bounds_are_rejected() {
+        for (side, value) in [
+            ("left", 900.0),
+            ("right", 0.0),
+            ("top", 500.0),
+            ("bottom", -1.0),
+        ] {
+            let result = load(|scene| scene["bounds"][side] = json!(value));
+            assert!(
+                matches!(result, Err(SceneError::InvalidBounds(_))),
+                "{:?}",
+                result
+            );
+        }
+    }
+}
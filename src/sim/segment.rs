@@ -1,9 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use super::vec2::*;
 use crate::vec2;
-use crate::vec2::*;
-use chain_cmp::chmp;
 
 pub type Vectuple = (Vec2, Vec2);
 
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Segment {
     pub point1: Vec2,
     pub point2: Vec2,
@@ -52,13 +54,25 @@ impl Segment {
         let u = ((x1 - x3) * (y1 - y2) - (y1 - y3) * (x1 - x2))
             / ((x1 - x2) * (y3 - y4) - (y1 - y2) * (x3 - x4));
 
-        if chmp!(0.0 <= t <= 1.0) && chmp!(0.0 <= u <= 1.0) {
+        if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
             Some(vec2!(x1 + t * (x2 - x1), y1 + t * (y2 - y1)))
         } else {
             None
         }
     }
 
+    /// Point on the segment closest to `point`.
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        let direction = self.point2 - self.point1;
+        let length_squared = direction.dot(direction);
+        if length_squared == 0.0 {
+            return self.point1;
+        }
+
+        let t = ((point - self.point1).dot(direction) / length_squared).clamp(0.0, 1.0);
+        self.point1 + direction * t
+    }
+
     pub fn len(&self) -> f64 {
         ((self.point2.x - self.point1.x).powi(2) + (self.point2.y - self.point1.y).powi(2)).sqrt()
     }
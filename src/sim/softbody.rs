@@ -0,0 +1,113 @@
+use super::arena::{Arena, ObjectId};
+use super::object::Object;
+use super::vec2::Vec2;
+
+/// A closed ring of objects that keeps the area it encloses, like a balloon.
+///
+/// Only the area is constrained here: the ring is usually held together by distance links between neighbours as
+/// well. Solved on positions along with the other constraints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoftBody {
+    /// The objects around the ring, in order (either way round).
+    pub ring: Vec<ObjectId>,
+
+    /// Signed area enclosed by the ring when it was made, in square pixels.
+    pub rest_area: f64,
+
+    /// Area the ring is pushed towards, as a multiple of `rest_area`: below `1.0` it sags, above it inflates as far
+    /// as its links let it.
+    pub pressure: f64,
+}
+
+impl SoftBody {
+    /// A soft body made of `ring`, keeping the area it encloses in `objects` right now.
+    ///
+    /// `None` if the ring has fewer than three objects, or any of them isn't in `objects`.
+    pub fn new(ring: Vec<ObjectId>, objects: &Arena<Object>, pressure: f64) -> Option<SoftBody> {
+        if ring.len() < 3 {
+            return None;
+        }
+        let positions = ring
+            .iter()
+            .map(|&id| objects.get(id).map(|i| i.next_position))
+            .collect::<Option<Vec<Vec2>>>()?;
+
+        Some(SoftBody {
+            rest_area: area(&positions),
+            ring,
+            pressure,
+        })
+    }
+
+    /// Moves the ring's next positions so it encloses `rest_area * pressure`. Sleeping, kinematic and static objects
+    /// don't move.
+    pub fn project(&self, objects: &mut Arena<Object>) {
+        let Some(indices) = self
+            .ring
+            .iter()
+            .map(|&id| objects.index_of(id))
+            .collect::<Option<Vec<usize>>>()
+        else {
+            return;
+        };
+        if indices.len() < 3 {
+            return;
+        }
+
+        let positions: Vec<Vec2> = indices
+            .iter()
+            .map(|&idx| objects[idx].next_position)
+            .collect();
+        let gradients = area_gradients(&positions);
+        let weights: Vec<f64> = indices
+            .iter()
+            .map(|&idx| {
+                let i = &objects[idx];
+                if i.is_fixed() {
+                    0.0
+                } else {
+                    1.0 / i.mass
+                }
+            })
+            .collect();
+
+        let denominator: f64 = gradients
+            .iter()
+            .zip(&weights)
+            .map(|(gradient, w)| w * gradient.dot(*gradient))
+            .sum();
+        if denominator == 0.0 {
+            return;
+        }
+
+        // move every object along the gradient of the area, in proportion to its inverse mass
+        let error = area(&positions) - self.rest_area * self.pressure;
+        for ((&idx, gradient), w) in indices.iter().zip(&gradients).zip(&weights) {
+            objects[idx].next_position -= *gradient * (error * w / denominator);
+        }
+    }
+}
+
+/// Signed area enclosed by `points` (shoelace formula). Positive when they go clockwise on screen, where y points
+/// down.
+pub fn area(points: &[Vec2]) -> f64 {
+    (0..points.len())
+        .map(|idx| points[idx].cross(points[(idx + 1) % points.len()]))
+        .sum::<f64>()
+        / 2.0
+}
+
+/// How the signed area of `points` changes as each of them moves.
+fn area_gradients(points: &[Vec2]) -> Vec<Vec2> {
+    let n = points.len();
+
+    (0..n)
+        .map(|idx| {
+            let (previous, next) = (points[(idx + n - 1) % n], points[(idx + 1) % n]);
+            Vec2 {
+                x: (next.y - previous.y) / 2.0,
+                y: (previous.x - next.x) / 2.0,
+            }
+        })
+        .collect()
+}
@@ -1,225 +1,1295 @@
-use crate::Object;
-use crate::{vec2, Vec2};
+use std::collections::HashSet;
+use std::sync::Arc;
 
-use super::segment::Segment;
-use super::util::reduce2d;
+use super::arena::{Arena, ObjectId};
+use super::boundary::{Boundaries, BoundaryMode};
+use super::broadphase::BroadPhase;
+use super::collider::Collider;
+use super::collision::Contact;
+use super::constraint::Constraint;
+use super::diagnostics::Diagnostics;
+use super::force::ForceModel;
+use super::integrator::{Integrator, SemiImplicitEuler};
+use super::object::{combine, BodyType, Object};
+use super::params::PhysicsParams;
+use super::softbody::SoftBody;
+use super::vec2::Vec2;
 
-/// Gravity constant.
-/// Measured in pixels per second squared
-const GRAVITY: Vec2 = vec2!(0, 1000); // in pixels per second squared
+/// Length of a fixed step.
+/// Measured in seconds
+pub const FIXED_DT: f64 = 1.0 / 120.0;
 
-/// Bounce Constant (aka deflective efficacy).
-///
-/// Represents how much energy is deflected off the floor back into the ball.
-///
-/// The following equation is always satisfied (in real life terms):
-/// ```
-/// 0.0 < BOUNCE_CONSTANT < 1.0
-/// ```
-const BOUNCE_CONSTANT: f64 = 0.60;
+/// Most fixed steps taken per call to `Solver::step`.
+const MAX_SUBSTEPS: usize = 8;
 
-/// Friction constant.
-///
-/// Represents how much friction the ball experiences when touching the ground.
-const FRICTION: f64 = 0.01;
+/// Most passes `solve_collision_objects` makes to push overlapping objects apart. Each pass lifts a stack by about
+/// one more object, so this is plenty for tall ones; most steps stop long before.
+const MAX_PENETRATION_ITERATIONS: usize = 64;
+
+/// Overlap between objects left over after `solve_collision_objects`, below which it stops pushing them apart.
+/// Measured in pixels
+const PENETRATION_TOLERANCE: f64 = 0.01;
+
+/// Passes `solve_constraints` makes over every distance and rope link, so that chains of them settle.
+const CONSTRAINT_ITERATIONS: usize = 8;
 
+/// Largest gap between two objects that still counts as them touching, e.g. to wake whatever rested on a removed
+/// object.
+/// Measured in pixels
+const CONTACT_SLOP: f64 = 1.0;
+
+/// Objects followed by their copies across wrapping sides, and for each one the index of the object it is a copy of
+/// and how far it was moved.
+type Images = (Vec<Object>, Vec<(usize, Vec2)>);
+
+#[derive(Clone)]
 pub struct Solver {
-    pub objects: Vec<Object>,
+    /// Every object, addressed by index like a slice or by the `ObjectId` it was added with.
+    pub objects: Arena<Object>,
     pub bound_left: f64,
     pub bound_right: f64,
     pub bound_top: f64,
     pub bound_bottom: f64,
+
+    /// What happens at each side of the bounds.
+    pub boundaries: Boundaries,
+
+    /// Static obstacles inside the bounds.
+    pub colliders: Vec<Collider>,
+
+    /// Links between objects. Links to objects that have been removed are dropped along with them.
+    pub constraints: Vec<Constraint>,
+
+    /// Rings of objects keeping the area inside them. Dropped once any of their objects is removed.
+    pub soft_bodies: Vec<SoftBody>,
+
+    /// Physical constants: gravity, bounciness, friction, and so on.
+    pub params: PhysicsParams,
+
+    /// Strategy used to find candidate pairs for collision detection.
+    pub broad_phase: BroadPhase,
+
+    /// How objects are accelerated by gravity.
+    pub force_model: ForceModel,
+
+    /// Numerical scheme used to advance objects through time.
+    pub integrator: Arc<dyn Integrator>,
+
+    /// Length of a step taken by `step`, in seconds.
+    pub fixed_dt: f64,
+
+    /// Most steps `step` will take in one call. Time beyond that is dropped, so that a slow frame can't make the
+    /// next one even slower (the "spiral of death").
+    pub max_substeps: usize,
+
+    /// Time passed to `step` that hasn't been simulated yet.
+    accumulator: f64,
+
+    /// Simulated time so far, in seconds.
+    pub time: f64,
 }
 
 impl Solver {
     pub fn new(bound_left: f64, bound_right: f64, bound_top: f64, bound_bottom: f64) -> Solver {
         Solver {
-            objects: vec![],
+            objects: Arena::new(),
+            colliders: vec![],
+            constraints: vec![],
+            soft_bodies: vec![],
             bound_left,
             bound_right,
             bound_top,
             bound_bottom,
+            boundaries: Boundaries::default(),
+            params: PhysicsParams::default(),
+            broad_phase: BroadPhase::default(),
+            force_model: ForceModel::default(),
+            integrator: Arc::new(SemiImplicitEuler),
+            fixed_dt: FIXED_DT,
+            max_substeps: MAX_SUBSTEPS,
+            accumulator: 0.0,
+            time: 0.0,
         }
     }
 
-    pub fn add_object(&mut self, position: Vec2, velocity: Vec2, mass: f64) {
-        self.objects.push(Object {
-            position,
-            velocity,
-            next_position: position,
-            next_velocity: velocity,
-            mass,
-        });
+    pub fn add_object(&mut self, position: Vec2, velocity: Vec2, mass: f64) -> ObjectId {
+        self.add(Object::new(position, velocity, mass))
     }
 
-    pub fn solve_collision_walls(&mut self) {
+    /// Adds an already configured object, e.g. one built with `Object::new(..).with_radius(..)`.
+    pub fn add(&mut self, object: Object) -> ObjectId {
+        self.objects.insert(object)
+    }
+
+    /// Removes an object, returning it, or `None` if it was already removed.
+    ///
+    /// The last object takes its place in `objects`, so indices shift but every other id stays valid. Objects that
+    /// were touching it are woken up, in case they were resting on it.
+    pub fn remove(&mut self, id: ObjectId) -> Option<Object> {
+        let object = self.objects.remove(id)?;
+        self.wake_touching(&object);
+        self.prune_constraints();
+        Some(object)
+    }
+
+    /// Wakes every object touching `removed`, which just left, so nothing stays asleep resting on thin air.
+    fn wake_touching(&mut self, removed: &Object) {
+        for i in &mut self.objects {
+            if Vec2::dist_scalar(i.position, removed.position)
+                <= i.radius + removed.radius + CONTACT_SLOP
+            {
+                i.wake();
+            }
+        }
+    }
+
+    /// Removes every object, and every constraint and soft body made of them.
+    pub fn clear(&mut self) {
+        self.objects.clear();
+        self.constraints.clear();
+        self.soft_bodies.clear();
+    }
+
+    /// Links two objects, waking them both up.
+    pub fn connect(&mut self, constraint: Constraint) {
+        self.wake(constraint.a);
+        self.wake(constraint.b);
+        self.constraints.push(constraint);
+    }
+
+    /// Drops the constraints and soft bodies of objects that have been removed.
+    fn prune_constraints(&mut self) {
+        let objects = &self.objects;
+        self.constraints
+            .retain(|c| objects.contains(c.a) && objects.contains(c.b));
+        self.soft_bodies
+            .retain(|body| body.ring.iter().all(|&id| objects.contains(id)));
+    }
+
+    pub fn get(&self, id: ObjectId) -> Option<&Object> {
+        self.objects.get(id)
+    }
+
+    /// Sleeping objects ignore changes to their velocity until woken with `wake`.
+    pub fn get_mut(&mut self, id: ObjectId) -> Option<&mut Object> {
+        self.objects.get_mut(id)
+    }
+
+    /// Makes an object dynamic, kinematic or static, and wakes it up. An object made static stops dead.
+    pub fn set_body_type(&mut self, id: ObjectId, body_type: BodyType) {
+        if let Some(object) = self.objects.get_mut(id) {
+            object.body_type = body_type;
+            if body_type == BodyType::Static {
+                object.velocity = Vec2 { x: 0.0, y: 0.0 };
+                object.next_velocity = object.velocity;
+                object.angular_velocity = 0.0;
+                object.next_angular_velocity = 0.0;
+            }
+            object.wake();
+        }
+    }
+
+    /// Wakes an object up, so it is simulated again.
+    pub fn wake(&mut self, id: ObjectId) {
+        if let Some(object) = self.objects.get_mut(id) {
+            object.wake();
+        }
+    }
+
+    /// Wakes every object up, e.g. after changing gravity or the colliders they might be resting on.
+    pub fn wake_all(&mut self) {
         for i in &mut self.objects {
+            i.wake();
+        }
+    }
+
+    /// Every object with its id.
+    pub fn iter(&self) -> impl Iterator<Item = (ObjectId, &Object)> {
+        self.objects.entries()
+    }
+
+    /// The object covering `position`, e.g. to pick one with the mouse. The latest added wins where they overlap.
+    pub fn object_at(&self, position: Vec2) -> Option<ObjectId> {
+        self.objects
+            .entries()
+            .filter(|(_, i)| Vec2::dist_scalar(i.position, position) <= i.radius)
+            .map(|(id, _)| id)
+            .last()
+    }
+
+    /// Applies each side's `BoundaryMode` to every object that reached it during the last step.
+    ///
+    /// Bouncing objects are sent back in, wrapping objects are moved to the opposite side, and open or absorbing
+    /// sides remove the objects that left or touched them.
+    pub fn solve_collision_walls(&mut self) {
+        let width = self.bound_right - self.bound_left;
+        let height = self.bound_bottom - self.bound_top;
+        let mut removed: Vec<ObjectId> = vec![];
+        let resting_speed = self.params.resting_speed;
+
+        for (id, i) in self.objects.entries_mut() {
+            let restitution = combine(i.restitution, self.params.restitution);
+            let friction = combine(i.friction, self.params.friction);
+            // kinematic objects go wherever they are steered, so only dynamic ones bounce
+            let dynamic = i.body_type == BodyType::Dynamic;
+
+            // speed an object hitting the wall at `speed` leaves it with: too slow, and it comes to rest against it
+            let bounce = |speed: f64| {
+                if speed < resting_speed {
+                    0.0
+                } else {
+                    speed * restitution
+                }
+            };
+
             // check for collision with wall
-            if i.position.y > self.bound_bottom {
-                i.next_velocity.y = -(i.velocity.y.abs() * BOUNCE_CONSTANT);
-                i.next_position.y = self.bound_bottom - 0.05;
+            if i.position.y + i.radius >= self.bound_bottom {
+                match self.boundaries.bottom {
+                    BoundaryMode::Bounce if dynamic => {
+                        i.next_velocity.y = -bounce(i.velocity.y.abs());
+                        i.next_position.y = self.bound_bottom - i.radius - self.params.wall_offset;
+
+                        i.apply_surface_friction(
+                            Vec2 { x: 0.0, y: -1.0 },
+                            i.next_velocity.y - i.velocity.y,
+                            friction,
+                        );
+                    }
+                    BoundaryMode::Wrap if i.position.y > self.bound_bottom => {
+                        i.translate(Vec2 { x: 0.0, y: -height })
+                    }
+                    BoundaryMode::Open if i.position.y - i.radius > self.bound_bottom => {
+                        removed.push(id)
+                    }
+                    BoundaryMode::Absorb => removed.push(id),
+                    _ => {}
+                }
+            } else if i.position.y - i.radius <= self.bound_top {
+                match self.boundaries.top {
+                    BoundaryMode::Bounce if dynamic => {
+                        i.next_velocity.y = bounce(i.velocity.y.abs());
+                        i.next_position.y = self.bound_top + i.radius + self.params.wall_offset;
+
+                        i.apply_surface_friction(
+                            Vec2 { x: 0.0, y: 1.0 },
+                            i.next_velocity.y - i.velocity.y,
+                            friction,
+                        );
+                    }
+                    BoundaryMode::Wrap if i.position.y < self.bound_top => {
+                        i.translate(Vec2 { x: 0.0, y: height })
+                    }
+                    BoundaryMode::Open if i.position.y + i.radius < self.bound_top => {
+                        removed.push(id)
+                    }
+                    BoundaryMode::Absorb => removed.push(id),
+                    _ => {}
+                }
+            }
+
+            if i.position.x - i.radius <= self.bound_left {
+                match self.boundaries.left {
+                    BoundaryMode::Bounce if dynamic => {
+                        i.next_velocity.x = bounce(i.velocity.x.abs());
+                        i.next_position.x = self.bound_left + i.radius + self.params.wall_offset;
+
+                        i.apply_surface_friction(
+                            Vec2 { x: 1.0, y: 0.0 },
+                            i.next_velocity.x - i.velocity.x,
+                            friction,
+                        );
+                    }
+                    BoundaryMode::Wrap if i.position.x < self.bound_left => {
+                        i.translate(Vec2 { x: width, y: 0.0 })
+                    }
+                    BoundaryMode::Open if i.position.x + i.radius < self.bound_left => {
+                        removed.push(id)
+                    }
+                    BoundaryMode::Absorb => removed.push(id),
+                    _ => {}
+                }
+            } else if i.position.x + i.radius >= self.bound_right {
+                match self.boundaries.right {
+                    BoundaryMode::Bounce if dynamic => {
+                        i.next_velocity.x = -bounce(i.velocity.x.abs());
+                        i.next_position.x = self.bound_right - i.radius - self.params.wall_offset;
+
+                        i.apply_surface_friction(
+                            Vec2 { x: -1.0, y: 0.0 },
+                            i.next_velocity.x - i.velocity.x,
+                            friction,
+                        );
+                    }
+                    BoundaryMode::Wrap if i.position.x > self.bound_right => {
+                        i.translate(Vec2 { x: -width, y: 0.0 })
+                    }
+                    BoundaryMode::Open if i.position.x - i.radius > self.bound_right => {
+                        removed.push(id)
+                    }
+                    BoundaryMode::Absorb => removed.push(id),
+                    _ => {}
+                }
+            }
+        }
+
+        for id in removed {
+            if let Some(object) = self.objects.remove(id) {
+                self.wake_touching(&object);
+            }
+        }
+        self.prune_constraints();
+    }
+
+    /// Pushes objects back out of the bouncing walls they ended the step inside of, e.g. pressed into the floor by
+    /// the objects resting on top of them.
+    ///
+    /// An object moving into the wall slower than `resting_speed` is stopped there. Anything faster keeps its
+    /// velocity and bounces off at the start of the next step. Kinematic and static objects are left where they are.
+    pub fn solve_wall_penetration(&mut self) {
+        let resting_speed = self.params.resting_speed;
+
+        // stops the object against a wall, returning the speed it lost so friction can act on it
+        let rest = |speed: &mut f64| {
+            if speed.abs() < resting_speed {
+                std::mem::take(speed)
+            } else {
+                0.0
+            }
+        };
+
+        for i in &mut self.objects {
+            if i.body_type != BodyType::Dynamic {
+                continue;
+            }
 
-                i.next_velocity.x = i.velocity.x * (1.0 - FRICTION);
-            } else if i.position.y < self.bound_top {
-                i.next_velocity.y = i.velocity.y.abs() * BOUNCE_CONSTANT;
-                i.next_position.y = self.bound_top + 0.05;
+            let friction = combine(i.friction, self.params.friction);
+
+            if self.boundaries.bottom == BoundaryMode::Bounce
+                && i.next_position.y + i.radius > self.bound_bottom
+            {
+                i.next_position.y = self.bound_bottom - i.radius;
+                let lost = rest(&mut i.next_velocity.y);
+                i.apply_surface_friction(Vec2 { x: 0.0, y: -1.0 }, lost, friction);
+            }
+            if self.boundaries.top == BoundaryMode::Bounce
+                && i.next_position.y - i.radius < self.bound_top
+            {
+                i.next_position.y = self.bound_top + i.radius;
+                let lost = rest(&mut i.next_velocity.y);
+                i.apply_surface_friction(Vec2 { x: 0.0, y: 1.0 }, lost, friction);
+            }
+            if self.boundaries.left == BoundaryMode::Bounce
+                && i.next_position.x - i.radius < self.bound_left
+            {
+                i.next_position.x = self.bound_left + i.radius;
+                let lost = rest(&mut i.next_velocity.x);
+                i.apply_surface_friction(Vec2 { x: 1.0, y: 0.0 }, lost, friction);
+            }
+            if self.boundaries.right == BoundaryMode::Bounce
+                && i.next_position.x + i.radius > self.bound_right
+            {
+                i.next_position.x = self.bound_right - i.radius;
+                let lost = rest(&mut i.next_velocity.x);
+                i.apply_surface_friction(Vec2 { x: -1.0, y: 0.0 }, lost, friction);
+            }
+        }
+    }
+
+    /// Bounces every object off the static colliders it touches or would pass through by the end of the step.
+    ///
+    /// The velocity is reflected about the surface normal, losing some of it to restitution, and friction spins the
+    /// object up like the bounds do. Sleeping, kinematic and static objects are left alone.
+    pub fn solve_collision_colliders(&mut self) {
+        for i in &mut self.objects {
+            if i.is_fixed() {
+                continue;
+            }
+
+            let restitution = combine(i.restitution, self.params.restitution);
+            let friction = combine(i.friction, self.params.friction);
+
+            for collider in &self.colliders {
+                let Some(contact) = collider.contact(i.position, i.next_position, i.radius) else {
+                    continue;
+                };
+                let normal = contact.normal;
+
+                i.next_position = contact.position + normal * self.params.wall_offset;
+
+                let normal_speed = i.next_velocity.dot(normal);
+                if normal_speed < 0.0 {
+                    let restitution = if -normal_speed < self.params.resting_speed {
+                        0.0
+                    } else {
+                        restitution
+                    };
+                    let normal_impulse = -normal_speed * (1.0 + restitution);
+                    i.next_velocity += normal * normal_impulse;
+                    i.apply_surface_friction(normal, normal_impulse, friction);
+                }
+            }
+        }
+    }
 
-                i.next_velocity.x = i.velocity.x * (1.0 - FRICTION);
+    /// Applies the force of every spring over `dt` seconds.
+    pub fn solve_springs(&mut self, dt: f64) {
+        for idx in 0..self.constraints.len() {
+            let constraint = self.constraints[idx];
+            if let Some((object1, object2)) = self.pair_mut(constraint.a, constraint.b) {
+                let weights = (inverse_mass(object1), inverse_mass(object2));
+                constraint.apply_force(object1, object2, weights, dt);
             }
+        }
+    }
+
+    /// Moves objects so that every distance and rope link holds, and every soft body keeps its area, at the end of
+    /// the step.
+    ///
+    /// This is position-based dynamics: the links are satisfied by moving the objects, and then whatever the linked
+    /// objects were moved by over `dt` seconds is added to their velocities. Sleeping, kinematic and static objects
+    /// aren't moved, like in collisions, so an object hanging off a static one swings around it and one hanging off
+    /// a kinematic one is dragged along. Objects without links are left alone.
+    pub fn solve_constraints(&mut self, dt: f64) {
+        if self.constraints.is_empty() && self.soft_bodies.is_empty() {
+            return;
+        }
+
+        // where each linked object was before it was moved
+        let mut before: Vec<(usize, Vec2)> = self
+            .constraints
+            .iter()
+            .flat_map(|c| [c.a, c.b])
+            .chain(
+                self.soft_bodies
+                    .iter()
+                    .flat_map(|body| body.ring.iter().copied()),
+            )
+            .filter_map(|id| self.objects.index_of(id))
+            .map(|idx| (idx, self.objects[idx].next_position))
+            .collect();
+        before.sort_by_key(|&(idx, _)| idx);
+        before.dedup_by_key(|&mut (idx, _)| idx);
 
-            if i.position.x < self.bound_left {
-                i.next_velocity.x = i.velocity.x.abs() * BOUNCE_CONSTANT;
-                i.next_position.x = self.bound_left + 0.05;
+        for _ in 0..CONSTRAINT_ITERATIONS {
+            for body in &self.soft_bodies {
+                body.project(&mut self.objects);
+            }
 
-                i.next_velocity.y = i.velocity.y * (1.0 - FRICTION);
-            } else if i.position.x > self.bound_right {
-                i.next_velocity.x = -(i.velocity.x.abs() * BOUNCE_CONSTANT);
-                i.next_position.x = self.bound_right - 0.05;
+            for idx in 0..self.constraints.len() {
+                let constraint = self.constraints[idx];
+                let Some((object1, object2)) = self.pair_mut(constraint.a, constraint.b) else {
+                    continue;
+                };
 
-                i.next_velocity.y = i.velocity.y * (1.0 - FRICTION);
+                let weights = (inverse_mass(object1), inverse_mass(object2));
+                if weights.0 + weights.1 > 0.0 {
+                    constraint.project(object1, object2, weights);
+                }
             }
         }
+
+        for (idx, before) in before {
+            let i = &mut self.objects[idx];
+            i.next_velocity += (i.next_position - before) * (1.0 / dt);
+        }
     }
 
-    pub fn solve_euler(&mut self, dt: f64) {
+    /// Slows every object down by `air_drag` over `dt` seconds.
+    pub fn solve_drag(&mut self, dt: f64) {
+        if self.params.air_drag == 0.0 {
+            return;
+        }
+
+        let factor = (-self.params.air_drag * dt).exp();
         for i in &mut self.objects {
-            // next_velocity = current_velocity + dt*current_acceleration
-            // acceleration in this case === GRAVITY
-            i.next_velocity = i.next_velocity + GRAVITY * dt;
+            i.next_velocity *= factor;
+        }
+    }
+
+    /// Advances every object by `dt` seconds with the selected integrator and force model.
+    ///
+    /// Sleeping, kinematic and static objects still pull on the others, but nothing pulls on them: kinematic objects
+    /// carry on at their own velocity and spin, and the others stay exactly where they are.
+    pub fn integrate(&mut self, dt: f64) {
+        let (force_model, gravity) = (self.force_model, self.params.gravity);
+        let accelerations = |objects: &[Object]| force_model.accelerations(objects, gravity);
+
+        let fixed: Vec<usize> = self
+            .objects
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| i.is_fixed())
+            .map(|(idx, _)| idx)
+            .collect();
 
-            // next_position = current_position + dt*next_velocity
-            i.next_position = i.next_position + i.next_velocity * dt;
+        self.integrator
+            .integrate(&mut self.objects, dt, &accelerations);
+
+        // nothing applies a torque between collisions, so spin just carries on
+        for i in &mut self.objects {
+            i.next_angle = i.angle + i.next_angular_velocity * dt;
+        }
+
+        for idx in fixed {
+            let i = &mut self.objects[idx];
+            if i.body_type == BodyType::Kinematic && !i.asleep {
+                i.next_velocity = i.velocity;
+                i.next_angular_velocity = i.angular_velocity;
+            } else {
+                i.next_velocity = Vec2 { x: 0.0, y: 0.0 };
+                i.next_angular_velocity = 0.0;
+            }
+            i.next_position = i.position + i.next_velocity * dt;
+            i.next_angle = i.angle + i.next_angular_velocity * dt;
         }
     }
 
-    pub fn get_collisions(&self) -> Vec<usize> {
-        let mut collisions: Vec<Vec<usize>> = vec![];
+    /// Copies of the objects near wrapping sides, moved to just beyond the opposite side, so that objects can
+    /// collide across the seam.
+    ///
+    /// `None` if no side wraps.
+    fn periodic_images(&self) -> Option<Images> {
+        let (wraps_x, wraps_y) = (self.boundaries.wraps_x(), self.boundaries.wraps_y());
+        if !wraps_x && !wraps_y {
+            return None;
+        }
+
+        let width = self.bound_right - self.bound_left;
+        let height = self.bound_bottom - self.bound_top;
+
+        // two objects can only touch if they come within this distance of each other during the step
+        let reach = 2.0
+            * self
+                .objects
+                .iter()
+                .map(|i| i.radius + (i.next_position - i.position).len())
+                .fold(0.0, f64::max);
+
+        let mut images: Vec<Object> = self.objects.to_vec();
+        let mut origins: Vec<(usize, Vec2)> = (0..images.len())
+            .map(|idx| (idx, Vec2 { x: 0.0, y: 0.0 }))
+            .collect();
+
         for (idx, i) in self.objects.iter().enumerate() {
-            let mut collision_vec: Vec<usize> = vec![idx];
-            let i_segment = Segment::from((i.position, i.next_position)).extend(i.mass);
-
-            for (jdx, j) in self.objects.iter().enumerate() {
-                let j_segment = Segment::from((j.position, j.next_position));
-
-                if let Some(_) = Segment::intersection(&i_segment, &j_segment) {
-                    // this function only checks for
-                    // collisions, and we can throw
-                    // away the value
-                    if !collision_vec.contains(&jdx) {
-                        collision_vec.push(jdx);
+            let low = Vec2 {
+                x: i.position.x.min(i.next_position.x) - i.radius,
+                y: i.position.y.min(i.next_position.y) - i.radius,
+            };
+            let high = Vec2 {
+                x: i.position.x.max(i.next_position.x) + i.radius,
+                y: i.position.y.max(i.next_position.y) + i.radius,
+            };
+
+            let mut xs = vec![0.0];
+            if wraps_x && low.x < self.bound_left + reach {
+                xs.push(width);
+            }
+            if wraps_x && high.x > self.bound_right - reach {
+                xs.push(-width);
+            }
+            let mut ys = vec![0.0];
+            if wraps_y && low.y < self.bound_top + reach {
+                ys.push(height);
+            }
+            if wraps_y && high.y > self.bound_bottom - reach {
+                ys.push(-height);
+            }
+
+            for &x in &xs {
+                for &y in &ys {
+                    if x == 0.0 && y == 0.0 {
+                        continue;
                     }
+
+                    let offset = Vec2 { x, y };
+                    let mut image = i.clone();
+                    image.translate(offset);
+                    images.push(image);
+                    origins.push((idx, offset));
                 }
             }
+        }
+
+        Some((images, origins))
+    }
+
+    /// Returns every contact between two objects within the next step, ordered by time of impact. Pairs that can't
+    /// move each other, such as two sleeping objects, are skipped.
+    ///
+    /// Across wrapping sides, objects also touch the copies of each other beyond the opposite side; such contacts
+    /// record how far `b` was moved in `Contact::offset`.
+    pub fn get_collisions(&self) -> Vec<Contact> {
+        let ids = self.objects.ids();
+        let mut collisions: Vec<Contact> = vec![];
+
+        if let Some((images, origins)) = self.periodic_images() {
+            // the same pair can turn up twice, once from each side of the seam
+            let mut seen: HashSet<(usize, usize, u64, u64)> = HashSet::new();
+            let zero = Vec2 { x: 0.0, y: 0.0 };
 
-            if collision_vec.len() > 1 {
-                collisions.push(collision_vec);
+            for (idx, jdx) in self.broad_phase.candidate_pairs(&images) {
+                let (mut a, mut b) = (origins[idx], origins[jdx]);
+                if a.0 == b.0 || (a.1 != zero && b.1 != zero) {
+                    continue;
+                }
+                if a.0 > b.0 {
+                    [a, b] = [b, a];
+                }
+
+                if !can_collide(&self.objects[a.0], &self.objects[b.0]) {
+                    continue;
+                }
+
+                let offset = b.1 - a.1;
+                if !seen.insert((a.0, b.0, offset.x.to_bits(), offset.y.to_bits())) {
+                    continue;
+                }
+
+                let i = &self.objects[a.0];
+                let mut j = self.objects[b.0].clone();
+                j.translate(offset);
+
+                if let Some(contact) = sweep_or_rest(ids[a.0], i, ids[b.0], &j) {
+                    collisions.push(Contact { offset, ..contact });
+                }
+            }
+        } else {
+            for (idx, jdx) in self.broad_phase.candidate_pairs(&self.objects) {
+                let (i, j) = (&self.objects[idx], &self.objects[jdx]);
+                if !can_collide(i, j) {
+                    continue;
+                }
+
+                if let Some(contact) = sweep_or_rest(ids[idx], i, ids[jdx], j) {
+                    collisions.push(contact);
+                }
             }
         }
 
-        reduce2d(collisions)
+        collisions.sort_by(|a, b| a.time_of_impact.total_cmp(&b.time_of_impact));
+        collisions
     }
 
-    pub fn solve_collision_objects(&mut self) {
+    pub fn solve_collision_objects(&mut self, dt: f64) {
         /*
-            solve collisions by going over each object and checking if any other object will collide with it within the next
-            frame. if it is found to be colliding, then its index will be added to an array. this array, after finishing
-            the loop for each object, will be added to an array
-
+            solve collisions by sweeping each pair of objects over the next frame and, in order of time of impact,
+            exchanging momentum between them along the contact normal.
         */
-        let mut collisions: Vec<Vec<usize>> = vec![];
+        let contacts = self.get_collisions();
+        for contact in &contacts {
+            self.collide(contact, dt);
+        }
+
+        // objects resting on each other on the floor push each other back into it, so go over them until nothing
+        // overlaps any more for stacks to settle
+        for _ in 0..MAX_PENETRATION_ITERATIONS {
+            self.solve_wall_penetration();
+            let mut deepest: f64 = 0.0;
+            for contact in &contacts {
+                deepest = deepest.max(self.separate(contact));
+            }
+            if deepest <= PENETRATION_TOLERANCE {
+                break;
+            }
+        }
+    }
+
+    /// Resolves a contact between two objects using an impulse along the contact normal.
+    ///
+    /// Both objects are rewound to where they touch, exchange momentum, and then travel the remainder of the step
+    /// with their new velocities. The pair's restitution and friction are combined from both objects' materials, and
+    /// friction trades sliding at the point of contact for spin. Objects left overlapping are pushed apart in inverse
+    /// proportion to their masses. Contacts across a wrapping side are resolved as if `b` was moved by
+    /// `Contact::offset`.
+    ///
+    /// Below `PhysicsParams::resting_speed` nothing bounces. Sleeping, kinematic and static objects act as if they
+    /// were infinitely heavy; anything faster, or any kinematic object, wakes a sleeping one up.
+    #[allow(non_snake_case)]
+    pub fn collide(&mut self, contact: &Contact, dt: f64) {
+        let resting_speed = self.params.resting_speed;
+        let Some((object1, object2)) = self.pair_mut(contact.a, contact.b) else {
+            return;
+        };
+
+        let restitution = combine(object1.restitution, object2.restitution);
+        let friction = combine(object1.friction, object2.friction);
+
+        // positions at the time of impact
+        let t = contact.time_of_impact;
+        let x1 = object1.position + (object1.next_position - object1.position) * t;
+        let offset = contact.offset;
+        let x2 = object2.position + offset + (object2.next_position - object2.position) * t;
+
+        // earlier contacts in this step may have moved the objects, so prefer the current direction between them
+        let normal = if x1 != x2 {
+            (x2 - x1).normalize()
+        } else {
+            contact.normal
+        };
+
+        // a kinematic object shoves whatever it touches, however slowly it goes
+        let kinematic =
+            object1.body_type == BodyType::Kinematic || object2.body_type == BodyType::Kinematic;
+        let approach_speed = (object2.next_velocity - object1.next_velocity).dot(normal);
+        if kinematic || -approach_speed >= resting_speed {
+            object1.wake();
+            object2.wake();
+        }
+
+        let (w1, w2) = (inverse_mass(object1), inverse_mass(object2));
+        let W = w1 + w2;
+        if W == 0.0 {
+            return;
+        }
+
+        // only exchange momentum if the objects are moving towards each other
+        if approach_speed < 0.0 {
+            let restitution = if -approach_speed < resting_speed {
+                0.0
+            } else {
+                restitution
+            };
+
+            // impulse magnitude: j = -(1 + e) * v_rel . n / (1/m1 + 1/m2)
+            let impulse = -(1.0 + restitution) * approach_speed / W;
+
+            object1.next_velocity -= normal * (impulse * w1);
+            object2.next_velocity += normal * (impulse * w2);
+
+            contact_friction(object1, object2, normal, impulse, friction);
+
+            // travel the rest of the step with the new velocities
+            object1.next_position = x1 + object1.next_velocity * (dt * (1.0 - t));
+            object2.next_position = x2 + object2.next_velocity * (dt * (1.0 - t)) - offset;
+        }
+
+        // separate the objects so they no longer overlap
+        let (x1, x2) = (object1.next_position, object2.next_position + offset);
+        let penetration = object1.radius + object2.radius - Vec2::dist_scalar(x1, x2);
+        if penetration > 0.0 {
+            object1.next_position -= normal * (penetration * w1 / W);
+            object2.next_position += normal * (penetration * w2 / W);
+        }
     }
 
+    /// Pushes the two objects of a contact apart along the line between them, if they still overlap at the end of
+    /// the step, in inverse proportion to their masses.
+    ///
+    /// If they are touching and also moving into each other slower than `PhysicsParams::resting_speed`, that motion
+    /// is stopped. Returns how deep they overlapped.
     #[allow(non_snake_case)]
-    pub fn collide(&mut self, mut idx: usize, mut jdx: usize) {
-        if idx > jdx {
-            [idx, jdx] = [jdx, idx];
+    pub fn separate(&mut self, contact: &Contact) -> f64 {
+        let resting_speed = self.params.resting_speed;
+        let Some((object1, object2)) = self.pair_mut(contact.a, contact.b) else {
+            return 0.0;
+        };
+
+        let (x1, x2) = (
+            object1.next_position,
+            object2.next_position + contact.offset,
+        );
+        let penetration = object1.radius + object2.radius - Vec2::dist_scalar(x1, x2);
+        if penetration < -CONTACT_SLOP {
+            return 0.0;
+        }
+
+        let (w1, w2) = (inverse_mass(object1), inverse_mass(object2));
+        let W = w1 + w2;
+        if W == 0.0 {
+            return 0.0;
         }
-        let (head, tail) = self.objects.split_at_mut(idx + 1);
 
-        let object1 = &mut head[idx];
-        let object2 = &mut tail[jdx - idx - 1];
+        let normal = if x1 != x2 {
+            (x2 - x1).normalize()
+        } else {
+            contact.normal
+        };
+        if penetration > 0.0 {
+            object1.next_position -= normal * (penetration * w1 / W);
+            object2.next_position += normal * (penetration * w2 / W);
+        }
 
-        let (m1, m2) = (object1.mass, object2.mass);
-        let M = m1 + m2;
-        let (x1, x2) = (object1.position, object2.position);
+        // objects slowly pressing into each other are resting on each other: stop them, or the speed they gain
+        // from gravity every step keeps adding up
+        let approach_speed = (object2.next_velocity - object1.next_velocity).dot(normal);
+        if approach_speed < 0.0 && -approach_speed < resting_speed {
+            let impulse = -approach_speed / W;
+            object1.next_velocity -= normal * (impulse * w1);
+            object2.next_velocity += normal * (impulse * w2);
 
-        //object1.velocity = object1.velocity - ((2.0*m2)/M) *
+            let friction = combine(object1.friction, object2.friction);
+            contact_friction(object1, object2, normal, impulse, friction);
+        }
 
-        println!("collision between {:?} and {:?}", object1, object2);
+        penetration.max(0.0)
+    }
+
+    /// Both objects of a contact, in the same order, or `None` if either is gone or they are the same object.
+    fn pair_mut(&mut self, a: ObjectId, b: ObjectId) -> Option<(&mut Object, &mut Object)> {
+        let (idx, jdx) = (self.objects.index_of(a)?, self.objects.index_of(b)?);
+
+        if idx < jdx {
+            let (head, tail) = self.objects.split_at_mut(jdx);
+            Some((&mut head[idx], &mut tail[0]))
+        } else if idx > jdx {
+            let (head, tail) = self.objects.split_at_mut(idx);
+            Some((&mut tail[0], &mut head[jdx]))
+        } else {
+            None
+        }
+    }
+
+    /// Kinetic plus gravitational potential energy of every object.
+    ///
+    /// See `ForceModel::potential_energies` for how potential energy is measured and shared between objects.
+    pub fn energies(&self) -> Vec<f64> {
+        let potential = self
+            .force_model
+            .potential_energies(&self.objects, self.params.gravity);
+
+        self.objects
+            .iter()
+            .zip(potential)
+            .map(|(i, potential)| kinetic_energy(i) + potential)
+            .collect()
+    }
+
+    /// Total kinetic energy of every object, spin included.
+    pub fn kinetic_energy(&self) -> f64 {
+        self.objects.iter().map(kinetic_energy).sum()
+    }
+
+    /// Total gravitational potential energy under the current force model.
+    pub fn potential_energy(&self) -> f64 {
+        self.force_model
+            .potential_energies(&self.objects, self.params.gravity)
+            .iter()
+            .sum()
+    }
+
+    /// Kinetic plus potential energy. Conserved by mutual gravitation with perfectly elastic collisions and no walls
+    /// or drag in the way, so any change is error in the integrator or the collision handling.
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// Total linear momentum of every object.
+    pub fn momentum(&self) -> Vec2 {
+        self.objects
+            .iter()
+            .fold(Vec2 { x: 0.0, y: 0.0 }, |sum, i| sum + i.velocity * i.mass)
+    }
+
+    /// Total angular momentum of every object about `origin`, spin included. Positive is clockwise on screen, where
+    /// y points down.
+    pub fn angular_momentum(&self, origin: Vec2) -> f64 {
+        self.objects
+            .iter()
+            .map(|i| {
+                i.mass * (i.position - origin).cross(i.velocity) + i.inertia() * i.angular_velocity
+            })
+            .sum()
+    }
+
+    /// Snapshot of energy, momentum and angular momentum (about the centre of the bounds) right now.
+    pub fn diagnostics(&self) -> Diagnostics {
+        let (kinetic_energy, potential_energy) = (self.kinetic_energy(), self.potential_energy());
+        let centre = Vec2 {
+            x: (self.bound_left + self.bound_right) / 2.0,
+            y: (self.bound_top + self.bound_bottom) / 2.0,
+        };
+
+        Diagnostics {
+            time: self.time,
+            kinetic_energy,
+            potential_energy,
+            total_energy: kinetic_energy + potential_energy,
+            momentum: self.momentum(),
+            angular_momentum: self.angular_momentum(centre),
+        }
+    }
+
+    /// Hash of the position, velocity, angle and spin of every object, bit for bit.
+    ///
+    /// Two solvers with the same hash are (almost certainly) in exactly the same state. Uses 64-bit FNV-1a, so the
+    /// value is stable across runs, platforms and builds.
+    pub fn state_hash(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        let mut hash = OFFSET_BASIS;
+        let mut write = |value: u64| {
+            for byte in value.to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+            }
+        };
+
+        write(self.objects.len() as u64);
+        for i in &self.objects {
+            for value in [
+                i.position.x,
+                i.position.y,
+                i.velocity.x,
+                i.velocity.y,
+                i.angle,
+                i.angular_velocity,
+            ] {
+                write(value.to_bits());
+            }
+        }
+
+        hash
     }
 
     pub fn resolve(&mut self) {
         for i in &mut self.objects {
+            i.previous_position = i.position;
             i.position = i.next_position;
             i.velocity = i.next_velocity;
+            i.previous_angle = i.angle;
+            i.angle = i.next_angle;
+            i.angular_velocity = i.next_angular_velocity;
+        }
+    }
+
+    /// Puts objects that have been resting for `sleep_time` seconds to sleep.
+    ///
+    /// An object is resting while it moved slower than `sleep_speed` over the last step. Its velocity doesn't tell,
+    /// since an object resting on the floor still gains a step's worth of speed from gravity every step. Everything
+    /// is woken up if sleeping isn't allowed, or isn't safe because the force model isn't `UniformField`. Objects
+    /// linked by constraints or soft bodies only ever sleep and wake all together.
+    pub fn solve_sleep(&mut self, dt: f64) {
+        let allowed = self.params.allow_sleep && self.force_model == ForceModel::UniformField;
+
+        for i in &mut self.objects {
+            if !allowed {
+                i.wake();
+                continue;
+            }
+            if i.is_fixed() {
+                continue;
+            }
+
+            if Vec2::dist_scalar(i.position, i.previous_position) < self.params.sleep_speed * dt {
+                i.rest_time += dt;
+            } else {
+                i.rest_time = 0.0;
+            }
+
+            if i.rest_time >= self.params.sleep_time {
+                i.asleep = true;
+                i.velocity = Vec2 { x: 0.0, y: 0.0 };
+                i.next_velocity = i.velocity;
+                i.angular_velocity = 0.0;
+                i.next_angular_velocity = 0.0;
+            }
+        }
+
+        // linked objects sleep together: one that is awake keeps every object linked to it awake, without starting
+        // their rest over, so the whole group falls asleep once all of it has been resting long enough. Static and
+        // still kinematic objects don't count either way, and moving kinematic ones are always awake
+        let awake = |i: &Object| match i.body_type {
+            BodyType::Dynamic => Some(!i.asleep),
+            BodyType::Kinematic if i.velocity != Vec2 { x: 0.0, y: 0.0 } => Some(true),
+            BodyType::Kinematic | BodyType::Static => None,
+        };
+        let links: Vec<(ObjectId, ObjectId)> = self
+            .constraints
+            .iter()
+            .map(|c| (c.a, c.b))
+            .chain(self.soft_bodies.iter().flat_map(|body| {
+                (0..body.ring.len())
+                    .map(|idx| (body.ring[idx], body.ring[(idx + 1) % body.ring.len()]))
+            }))
+            .collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &(a, b) in &links {
+                let Some((object1, object2)) = self.pair_mut(a, b) else {
+                    continue;
+                };
+                match (awake(object1), awake(object2)) {
+                    (Some(awake1), Some(awake2)) if awake1 != awake2 => {}
+                    _ => continue,
+                }
+
+                object1.asleep = false;
+                object2.asleep = false;
+                changed = true;
+            }
         }
     }
 
     pub fn solve_all(&mut self, dt: f64) {
-        //self.solve_collision_objects();
         self.solve_collision_walls();
-        self.solve_euler(dt);
-        let collisions = self.get_collisions();
+        self.solve_drag(dt);
+        self.solve_springs(dt);
+        self.integrate(dt);
+        self.solve_collision_objects(dt);
+        self.solve_constraints(dt);
+        self.solve_wall_penetration();
+        self.solve_collision_colliders();
+        self.resolve();
+        self.solve_sleep(dt);
+        self.time += dt;
+    }
+
+    /// Advances the simulation by `frame_dt` seconds of real time in steps of exactly `fixed_dt`.
+    ///
+    /// Leftover time is carried over to the next call. Returns how far (between `0.0` and `1.0`) the leftover time
+    /// is into the next step, to be passed to `Object::interpolated_position` when drawing.
+    pub fn step(&mut self, frame_dt: f64) -> f64 {
+        self.step_with(frame_dt, |_| {})
+    }
 
-        if collisions.len() > 0 {
-            println!("{:?}", self.get_collisions());
+    /// Same as `step`, calling `on_step` with the solver after each fixed step taken, e.g. to export every step.
+    pub fn step_with(&mut self, frame_dt: f64, mut on_step: impl FnMut(&Solver)) -> f64 {
+        self.accumulator += frame_dt.max(0.0);
+
+        let mut substeps = 0;
+        while self.accumulator >= self.fixed_dt {
+            if substeps == self.max_substeps {
+                // falling behind: drop the time we can't catch up on
+                self.accumulator %= self.fixed_dt;
+                break;
+            }
+
+            self.solve_all(self.fixed_dt);
+            self.accumulator -= self.fixed_dt;
+            substeps += 1;
+            on_step(self);
         }
-        self.resolve()
+
+        self.accumulator / self.fixed_dt
     }
 
-    pub fn solve_for_x_seconds(
-        &self,
-        position: Vec2,
-        velocity: Vec2,
-        mass: f64,
-        t: i64,
-    ) -> Vec<Vec2> {
-        let tx5 = t * 10;
+    /// Predicts where `object` would go if it was added to the solver now.
+    ///
+    /// Runs a copy of the solver, other objects and all, through the exact same fixed steps `step` takes, so the
+    /// prediction matches what actually happens as long as nothing else changes in the meantime. Returns the
+    /// object's position every `interval` seconds (rounded to a whole number of steps) for `horizon` seconds, or
+    /// until it leaves through an open or absorbing side.
+    pub fn predict(&self, object: Object, horizon: f64, interval: f64) -> Vec<Vec2> {
+        let steps = (horizon / self.fixed_dt).round() as usize;
+        let steps_per_sample = ((interval / self.fixed_dt).round() as usize).max(1);
+
+        let mut solver = self.clone();
+        let id = solver.add(object);
 
         let mut result: Vec<Vec2> = vec![];
+        for step in 1..=steps {
+            solver.solve_all(solver.fixed_dt);
 
-        let mut i = Object {
-            position,
-            velocity,
-            next_position: vec2!(),
-            next_velocity: vec2!(),
-            mass,
-        };
+            let Some(object) = solver.objects.get(id) else {
+                break;
+            };
+            if step % steps_per_sample == 0 {
+                result.push(object.position);
+            }
+        }
+        result
+    }
+}
 
-        for _ in 0..tx5 {
-            if i.position.y > self.bound_bottom {
-                i.velocity.y = -(i.velocity.y.abs() * BOUNCE_CONSTANT);
-                i.position.y = self.bound_bottom - 0.05;
+/// Inverse mass of `i`: sleeping, kinematic and static objects act as if they were infinitely heavy, and nothing
+/// moves them.
+fn inverse_mass(i: &Object) -> f64 {
+    if i.is_fixed() {
+        0.0
+    } else {
+        1.0 / i.mass
+    }
+}
 
-                i.velocity.x = i.velocity.x * (1.0 - FRICTION);
-            } else if i.position.y < self.bound_top {
-                i.velocity.y = i.velocity.y.abs() * BOUNCE_CONSTANT;
-                i.position.y = self.bound_top + 0.05;
+/// Whether a contact between `i` and `j` could move either of them: not if neither can be pushed around, unless a
+/// kinematic object runs into a sleeping one and wakes it up.
+fn can_collide(i: &Object, j: &Object) -> bool {
+    let shoves = |i: &Object, j: &Object| i.body_type == BodyType::Kinematic && j.asleep;
+    !(i.is_fixed() && j.is_fixed()) || shoves(i, j) || shoves(j, i)
+}
 
-                i.velocity.x = i.velocity.x * (1.0 - FRICTION);
-            }
+/// Sweeps `i` and `j` over the step like `Contact::between`, but also returns a contact at the end of the step if
+/// they are merely touching there, within `CONTACT_SLOP`.
+///
+/// Objects in a stack fall together, so the sweep never sees them meet, yet they still hold each other up.
+fn sweep_or_rest(a: ObjectId, i: &Object, b: ObjectId, j: &Object) -> Option<Contact> {
+    Contact::between(a, i, i.radius, b, j, j.radius).or_else(|| {
+        let end = j.next_position - i.next_position;
+        let gap = end.len() - i.radius - j.radius;
+        (end.len() != 0.0 && gap <= CONTACT_SLOP).then(|| Contact {
+            a,
+            b,
+            time_of_impact: 1.0,
+            normal: end.normalize(),
+            depth: (-gap).max(0.0),
+            offset: Vec2 { x: 0.0, y: 0.0 },
+        })
+    })
+}
 
-            if i.position.x < self.bound_left {
-                i.velocity.x = i.velocity.x.abs() * BOUNCE_CONSTANT;
-                i.position.x = self.bound_left + 0.05;
+/// Kinetic energy of `i`, moving and spinning.
+fn kinetic_energy(i: &Object) -> f64 {
+    0.5 * i.mass * i.velocity.dot(i.velocity)
+        + 0.5 * i.inertia() * i.angular_velocity * i.angular_velocity
+}
 
-                i.velocity.y = i.velocity.y * (1.0 - FRICTION);
-            } else if i.position.x > self.bound_right {
-                i.velocity.x = -(i.velocity.x.abs() * BOUNCE_CONSTANT);
-                i.position.x = self.bound_right - 0.05;
+/// Applies Coulomb friction between two objects touching along `normal` (from `object1` to `object2`), after an
+/// impulse of `normal_impulse` along it.
+///
+/// The sliding velocity between the two surfaces at the point of contact is taken away, as far as `friction` times
+/// the normal impulse allows, spinning both objects. Sleeping, kinematic and static objects aren't moved or spun.
+fn contact_friction(
+    object1: &mut Object,
+    object2: &mut Object,
+    normal: Vec2,
+    normal_impulse: f64,
+    friction: f64,
+) {
+    let (offset1, offset2) = (normal * object1.radius, -normal * object2.radius);
+    let relative = object2.surface_velocity(offset2) - object1.surface_velocity(offset1);
+    let sliding = relative - normal * relative.dot(normal);
+    let sliding_speed = sliding.len();
+    if sliding_speed == 0.0 {
+        return;
+    }
 
-                i.velocity.y = i.velocity.y * (1.0 - FRICTION);
-            }
+    // inverse masses and moments of inertia
+    let inverse = |i: &Object| {
+        if i.is_fixed() {
+            (0.0, 0.0)
+        } else {
+            (1.0 / i.mass, 1.0 / i.inertia())
+        }
+    };
+    let ((w1, iw1), (w2, iw2)) = (inverse(object1), inverse(object2));
 
-            // next_velocity = current_velocity + dt*current_acceleration
-            // acceleration in this case === GRAVITY
-            i.velocity += GRAVITY * (t as f64) * 0.01;
+    let tangent = sliding * (1.0 / sliding_speed);
+    let (arm1, arm2) = (offset1.cross(tangent), offset2.cross(tangent));
+    let denominator = w1 + w2 + arm1 * arm1 * iw1 + arm2 * arm2 * iw2;
+    if denominator == 0.0 {
+        return;
+    }
+
+    // impulse that would stop the surfaces sliding, capped by Coulomb's law
+    let impulse = (sliding_speed / denominator).min(friction * normal_impulse.abs());
 
-            // next_position = current_position + dt*next_velocity
-            i.position += i.velocity * (t as f64) * 0.01;
+    object1.next_velocity += tangent * (impulse * w1);
+    object1.next_angular_velocity += arm1 * impulse * iw1;
+    object2.next_velocity -= tangent * (impulse * w2);
+    object2.next_angular_velocity -= arm2 * impulse * iw2;
+}
 
-            result.push(i.position);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec2;
+
+    /// Two perfectly elastic, frictionless balls of different masses, closing in on each other at `velocity1` and
+    /// `velocity2`.
+    fn elastic_pair(position2: Vec2, velocity1: Vec2, velocity2: Vec2) -> Solver {
+        let mut solver = Solver::new(0.0, 800.0, 0.0, 400.0);
+        for (position, velocity, mass) in [
+            (vec2!(100, 100), velocity1, 10.0),
+            (position2, velocity2, 20.0),
+        ] {
+            solver.add(
+                Object::new(position, velocity, mass)
+                    .with_restitution(1.0)
+                    .with_friction(0.0),
+            );
         }
-        result
+        solver
+    }
+
+    /// Moves every object on by `dt` seconds, resolving only the collisions between them.
+    fn collide_step(solver: &mut Solver, dt: f64) -> usize {
+        for i in &mut solver.objects {
+            i.next_position = i.position + i.velocity * dt;
+        }
+        let contacts = solver.get_collisions();
+        for contact in &contacts {
+            solver.collide(contact, dt);
+        }
+        solver.resolve();
+        contacts.len()
+    }
+
+    fn assert_conserved(mut solver: Solver) {
+        let (momentum, energy) = (solver.momentum(), solver.kinetic_energy());
+        // the head-on pair's momenta cancel out, so measure drift against the size of each ball's
+        let scale: f64 = solver
+            .objects
+            .iter()
+            .map(|i| (i.velocity * i.mass).len())
+            .sum();
+        assert_eq!(collide_step(&mut solver, 0.05), 1);
+
+        let drift = solver.momentum() - momentum;
+        assert!(
+            drift.len() < 1e-9 * scale,
+            "momentum drifted by {:?}",
+            drift
+        );
+        let drift = solver.kinetic_energy() - energy;
+        assert!(drift.abs() < 1e-9 * energy, "energy drifted by {}", drift);
+    }
+
+    #[test]
+    fn removing_an_object_wakes_whatever_rested_on_it() {
+        let mut solver = Solver::new(0.0, 800.0, 0.0, 400.0);
+        let bottom = solver.add(Object::new(vec2!(400, 390), vec2!(0, 0), 10.0));
+        let top = solver.add(Object::new(vec2!(400, 370), vec2!(0, 0), 10.0));
+        for _ in 0..240 {
+            solver.solve_all(FIXED_DT);
+        }
+        assert!(solver.get(top).unwrap().asleep);
+
+        solver.remove(bottom);
+        assert!(!solver.get(top).unwrap().asleep);
+        for _ in 0..60 {
+            solver.solve_all(FIXED_DT);
+        }
+        assert!(solver.get(top).unwrap().position.y > 385.0);
+    }
+
+    #[test]
+    fn a_tall_stack_comes_to_rest_and_sleeps() {
+        let mut solver = Solver::new(0.0, 800.0, 0.0, 400.0);
+        let ids: Vec<ObjectId> = (0..8)
+            .map(|k| {
+                solver.add(Object::new(
+                    vec2!(400, 390.0 - 20.0 * k as f64),
+                    vec2!(0, 0),
+                    10.0,
+                ))
+            })
+            .collect();
+        for _ in 0..2400 {
+            solver.step(FIXED_DT);
+        }
+
+        for (k, pair) in ids.windows(2).enumerate() {
+            let (below, above) = (solver.get(pair[0]).unwrap(), solver.get(pair[1]).unwrap());
+            assert!(below.asleep && above.asleep, "ball {} is still awake", k);
+            assert!(below.position.y - above.position.y > 20.0 - 0.05);
+        }
+        assert!((solver.get(ids[0]).unwrap().position.y - 390.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn head_on_elastic_collision_conserves_momentum_and_energy() {
+        assert_conserved(elastic_pair(vec2!(125, 100), vec2!(200, 0), vec2!(-100, 0)));
+    }
+
+    #[test]
+    fn oblique_elastic_collision_conserves_momentum_and_energy() {
+        let mut solver = elastic_pair(vec2!(125, 110), vec2!(200, 30), vec2!(-100, 0));
+        assert_conserved(solver.clone());
+
+        // the balls really did hit off-centre, leaving both moving across the line they came in on
+        collide_step(&mut solver, 0.05);
+        assert!(solver.objects.iter().all(|i| i.velocity.y != 0.0));
     }
 }
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use std::fmt;
 use std::ops;
 
@@ -8,7 +10,7 @@ pub type Point = (f64, f64);
 ///  - subtracted by another Vec2
 ///  - multiplied by a scalar value (`f64`)
 ///  - normalized
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Vec2 {
     pub x: f64,
     pub y: f64,
@@ -16,15 +18,30 @@ pub struct Vec2 {
 
 impl Vec2 {
     pub fn normalize(&self) -> Vec2 {
-        let len_squared = self.x * self.x + self.y * self.y;
-        let inv_sqrt = len_squared.sqrt();
+        let len = self.len();
+        if len == 0.0 {
+            return *self;
+        }
 
         Vec2 {
-            x: self.x * inv_sqrt,
-            y: self.y * inv_sqrt,
+            x: self.x / len,
+            y: self.y / len,
         }
     }
 
+    pub fn len(&self) -> f64 {
+        self.dot(*self).sqrt()
+    }
+
+    pub fn dot(&self, other: Vec2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The z component of the cross product of `self` and `other` treated as 3D vectors.
+    pub fn cross(&self, other: Vec2) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
     pub fn abs(self) -> Vec2 {
         Vec2 {
             x: self.x.abs(),
@@ -132,7 +149,9 @@ impl From<Vec2> for Point {
 ///  shorthand for writing `Vec2 { x, y }`
 ///  ## Usage
 ///  ```
-///  let a = Vec2 {x: 10, y: 20};
+///  use gravity_rust::{vec2, Vec2};
+///
+///  let a = Vec2 { x: 10.0, y: 20.0 };
 ///  let b = vec2!(10, 20);
 ///
 ///  assert_eq!(a, b); // true
@@ -140,15 +159,15 @@ impl From<Vec2> for Point {
 #[macro_export]
 macro_rules! vec2 {
     ($x:expr, $y:expr) => {
-        Vec2 {
+        $crate::sim::vec2::Vec2 {
             x: $x as f64,
             y: $y as f64,
         }
     };
     ($v:expr) => {
-        vec2!($v, $v)
+        $crate::vec2!($v, $v)
     };
     () => {
-        vec2!(0)
+        $crate::vec2!(0)
     };
 }